@@ -0,0 +1,85 @@
+//! The access to the attribute files, shared by the sysfs devices.
+
+use crate::secure::{self, OpenFiles};
+use crate::{logind, Error, Result};
+use std::fs;
+use std::path::Path;
+
+/// How a device reads and writes its attribute files: through the files
+/// opened by `open_within` if any, by path otherwise, and for the brightness,
+/// through logind when writing it is not permitted.
+pub(crate) struct Access<'a> {
+    pub(crate) files: Option<&'a OpenFiles>,
+    pub(crate) logind: Option<&'a logind::Session>,
+    pub(crate) via_logind: bool,
+    pub(crate) read_only: bool,
+}
+
+impl Access<'_> {
+    pub(crate) fn read_string(&self, path: &Path) -> Result<String> {
+        match self.files {
+            Some(files) => secure::read_open(files, path),
+            None => fs::read_to_string(path).map_err(|e| Error::io(path, e)),
+        }
+    }
+
+    pub(crate) fn read_i32(&self, path: &Path) -> Result<i32> {
+        let content = self.read_string(path)?;
+        content
+            .trim()
+            .parse::<i32>()
+            .map_err(|_| Error::parse(path, &content, "an integer"))
+    }
+
+    pub(crate) fn write(&self, path: &Path, content: &str) -> Result<()> {
+        if self.read_only {
+            return Err(Error::ReadOnly {
+                path: path.to_path_buf(),
+            });
+        }
+        match self.files {
+            Some(files) => secure::write_open(files, path, content),
+            None => fs::write(path, content).map_err(|e| Error::io(path, e)),
+        }
+    }
+
+    pub(crate) fn write_i32(&self, path: &Path, value: i32) -> Result<()> {
+        self.write(path, &value.to_string())
+    }
+
+    /// Writes the brightness `value`, which must lie in `min..=max`, to its
+    /// attribute `path`, or has logind set it on the device `name` of `subsystem`.
+    pub(crate) fn write_brightness(
+        &self,
+        path: &Path,
+        (subsystem, name): (&str, &str),
+        value: i32,
+        (min, max): (i32, i32),
+    ) -> Result<()> {
+        if value < min || value > max {
+            return Err(Error::invalid_value(
+                path,
+                value,
+                format!("{}..={}", min, max),
+            ));
+        }
+        let session = match self.logind {
+            Some(session) if self.via_logind && !self.read_only => {
+                return session.set_brightness(subsystem, name, value as u32);
+            }
+            Some(session) => session,
+            None => return self.write_i32(path, value),
+        };
+        match self.write_i32(path, value) {
+            // Only a refusal of logind itself is more telling than the original error.
+            Err(denied @ Error::PermissionDenied { .. }) => {
+                match session.set_brightness(subsystem, name, value as u32) {
+                    Err(e) if logind::is_refusal(&e) => Err(e),
+                    Err(_) => Err(denied),
+                    Ok(()) => Ok(()),
+                }
+            }
+            result => result,
+        }
+    }
+}
@@ -0,0 +1,609 @@
+//! The interface shared by every kind of backlight control.
+
+use crate::fade::{self, Curve};
+use crate::snapshot::{self, DeviceSnapshot};
+use crate::step::{self, Step};
+use crate::{
+    BacklightType, Device, Error, Level, PowerState, Result, Scale, ACTUAL_BRIGHTNESS, BRIGHTNESS,
+    MAX_BRIGHTNESS,
+};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+/// A control of the power and brightness of a backlight.
+///
+/// The sysfs [`Device`] is the main implementation, and [`MockBacklight`]
+/// keeps its state in memory. Other kinds of controls only need to provide
+/// the name, the power state and the raw brightness; the percentages, the
+/// steps, the fades and the snapshots are derived from them.
+///
+/// [`Device`]: struct.Device.html
+/// [`MockBacklight`]: struct.MockBacklight.html
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{Backlight, MockBacklight, PowerState};
+/// let devices: Vec<Box<dyn Backlight>> = vec![
+///     Box::new(MockBacklight::new("panel", 100)),
+///     Box::new(MockBacklight::new("keyboard", 3)),
+/// ];
+/// for device in &devices {
+///     device.set_brightness_percent(100.0).unwrap();
+///     assert_eq!(device.toggle().unwrap(), PowerState::Normal);
+/// }
+/// assert_eq!(devices[1].brightness().unwrap(), 3);
+/// ```
+pub trait Backlight: Send + Sync {
+    /// Returns the name of the backlight.
+    fn name(&self) -> &str;
+
+    /// Returns the path of the backlight, if it is backed by a folder.
+    fn path(&self) -> Option<&Path> {
+        None
+    }
+
+    /// Returns an identifier of the backlight that survives reboots,
+    /// which is its name by default.
+    fn id(&self) -> Result<String> {
+        Ok(self.name().to_string())
+    }
+
+    /// Returns the type of the backlight, [`BacklightType::Raw`] by default.
+    ///
+    /// [`BacklightType::Raw`]: enum.BacklightType.html#variant.Raw
+    fn backlight_type(&self) -> Result<BacklightType> {
+        Ok(BacklightType::Raw)
+    }
+
+    /// Returns the files whose modification signals a change of the
+    /// attributes, so that they can be watched with inotify.
+    ///
+    /// Backlights without such files return none and are polled.
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        Vec::new()
+    }
+
+    /// Reads the power state of the backlight.
+    fn power_state(&self) -> Result<PowerState>;
+
+    /// Sets the power state of the backlight.
+    fn set_power_state(&self, state: PowerState) -> Result<()>;
+
+    /// Reads the current raw brightness of the backlight.
+    fn brightness(&self) -> Result<i32>;
+
+    /// Reads the brightness actually reported by the hardware,
+    /// which is the requested one by default.
+    fn actual_brightness(&self) -> Result<i32> {
+        self.brightness()
+    }
+
+    /// Reads the maximum raw brightness of the backlight.
+    fn max_brightness(&self) -> Result<i32>;
+
+    /// Returns the lowest raw brightness that [`set_brightness`] accepts,
+    /// `0` by default.
+    ///
+    /// [`set_brightness`]: #tymethod.set_brightness
+    fn min_brightness(&self) -> Result<i32> {
+        Ok(0)
+    }
+
+    /// Returns the highest raw brightness that [`set_brightness`] accepts,
+    /// [`max_brightness`] by default.
+    ///
+    /// [`set_brightness`]: #tymethod.set_brightness
+    /// [`max_brightness`]: #tymethod.max_brightness
+    fn ceiling_brightness(&self) -> Result<i32> {
+        self.max_brightness()
+    }
+
+    /// Sets the brightness to the raw `value`, which must lie in
+    /// `min_brightness..=ceiling_brightness`.
+    fn set_brightness(&self, value: i32) -> Result<()>;
+
+    /// Sets the brightness to the raw `value`, ignoring [`min_brightness`]
+    /// and [`ceiling_brightness`].
+    ///
+    /// By default this is [`set_brightness`], for backlights without limits.
+    ///
+    /// [`min_brightness`]: #method.min_brightness
+    /// [`ceiling_brightness`]: #method.ceiling_brightness
+    /// [`set_brightness`]: #tymethod.set_brightness
+    fn force_brightness(&self, value: i32) -> Result<()> {
+        self.set_brightness(value)
+    }
+
+    /// Returns the scale used to convert percentages to raw values,
+    /// [`Scale::Linear`] by default.
+    ///
+    /// [`Scale::Linear`]: enum.Scale.html#variant.Linear
+    fn scale(&self) -> Scale {
+        Scale::Linear
+    }
+
+    /// Sets the minimum brightness that set and step operations respect,
+    /// as returned by [`min_brightness`].
+    ///
+    /// Backlights without limits return [`Error::Unsupported`] by default.
+    ///
+    /// [`min_brightness`]: #method.min_brightness
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_lcd::{Backlight, Device, Level, MockBacklight};
+    /// # use std::fs;
+    /// let path = std::env::temp_dir().join("rust-lcd-doc-set-floor");
+    /// # fs::create_dir_all(&path).unwrap();
+    /// fs::write(path.join("max_brightness"), "200\n").unwrap();
+    /// let mut devices: Vec<Box<dyn Backlight>> = vec![Box::new(Device::new(&path))];
+    /// devices[0].set_floor(Level::Percent(10.0)).unwrap();
+    /// assert_eq!(devices[0].min_brightness().unwrap(), 20);
+    ///
+    /// let mut mock = MockBacklight::new("panel", 100);
+    /// assert!(mock.set_floor(Level::Raw(5)).is_err());
+    /// # fs::remove_dir_all(&path).unwrap();
+    /// ```
+    fn set_floor(&mut self, floor: Level) -> Result<()> {
+        let _ = floor;
+        Err(unsupported(self, "a floor"))
+    }
+
+    /// Sets the maximum brightness that set and step operations respect,
+    /// as returned by [`ceiling_brightness`]; `None` disables it.
+    ///
+    /// Backlights without limits return [`Error::Unsupported`] by default.
+    ///
+    /// [`ceiling_brightness`]: #method.ceiling_brightness
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    fn set_ceiling(&mut self, ceiling: Option<Level>) -> Result<()> {
+        let _ = ceiling;
+        Err(unsupported(self, "a ceiling"))
+    }
+
+    /// Sets the scale used to convert percentages to raw values.
+    ///
+    /// Backlights with a fixed scale return [`Error::Unsupported`] by default.
+    ///
+    /// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+    fn set_scale(&mut self, scale: Scale) -> Result<()> {
+        let _ = scale;
+        Err(unsupported(self, "a scale"))
+    }
+
+    /// Turns the backlight ON, setting [`PowerState::Unblank`].
+    ///
+    /// [`PowerState::Unblank`]: enum.PowerState.html#variant.Unblank
+    fn power_on(&self) -> Result<()> {
+        self.set_power_state(PowerState::Unblank)
+    }
+
+    /// Turns the backlight OFF, setting [`PowerState::Normal`], i.e. writing
+    /// `1` to `bl_power` as this crate always did.
+    ///
+    /// Use [`set_power_state`](#tymethod.set_power_state) for the other blank levels.
+    ///
+    /// [`PowerState::Normal`]: enum.PowerState.html#variant.Normal
+    fn power_off(&self) -> Result<()> {
+        self.set_power_state(PowerState::Normal)
+    }
+
+    /// Toggles the state of the backlight ON and OFF.
+    ///
+    /// Any blank level counts as OFF and is turned into [`PowerState::Unblank`],
+    /// while [`PowerState::Unblank`] is turned into [`PowerState::Normal`],
+    /// as with [`power_off`](#method.power_off).
+    ///
+    /// The return value is either an [`Error`] or the new state of the backlight.
+    ///
+    /// [`PowerState::Unblank`]: enum.PowerState.html#variant.Unblank
+    /// [`PowerState::Normal`]: enum.PowerState.html#variant.Normal
+    /// [`Error`]: enum.Error.html
+    fn toggle(&self) -> Result<PowerState> {
+        let new_state = if self.power_state()?.is_on() {
+            PowerState::Normal
+        } else {
+            PowerState::Unblank
+        };
+        self.set_power_state(new_state)?;
+        Ok(new_state)
+    }
+
+    /// Reads the current brightness as a percentage, according to the [`scale`].
+    ///
+    /// [`scale`]: #method.scale
+    fn brightness_percent(&self) -> Result<f64> {
+        let value = self.brightness()?;
+        self.raw_to_percent(value)
+    }
+
+    /// Sets the brightness to `percent` of its maximum, according to the [`scale`].
+    ///
+    /// The percentage is clamped to `0.0..=100.0`, and the result is kept
+    /// within [`min_brightness`] and [`ceiling_brightness`]; a `NaN` is rejected.
+    /// The return value is the raw brightness that was written.
+    ///
+    /// [`scale`]: #method.scale
+    /// [`min_brightness`]: #method.min_brightness
+    /// [`ceiling_brightness`]: #method.ceiling_brightness
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_lcd::{Backlight, Device};
+    /// # use std::fs;
+    /// let path = std::env::temp_dir().join("rust-lcd-doc-brightness");
+    /// # fs::create_dir_all(&path).unwrap();
+    /// fs::write(path.join("max_brightness"), "200\n").unwrap();
+    /// fs::write(path.join("brightness"), "0\n").unwrap();
+    /// let dev = Device::new(&path);
+    /// assert_eq!(dev.set_brightness_percent(25.0).unwrap(), 50);
+    /// assert_eq!(dev.set_brightness_percent(150.0).unwrap(), 200);
+    /// assert!(dev.set_brightness(201).is_err());
+    /// assert_eq!(dev.brightness().unwrap(), 200);
+    /// # fs::remove_dir_all(&path).unwrap();
+    /// ```
+    fn set_brightness_percent(&self, percent: f64) -> Result<i32> {
+        let value = self
+            .percent_to_raw(percent)?
+            .min(self.ceiling_brightness()?)
+            .max(self.min_brightness()?);
+        self.set_brightness(value)?;
+        Ok(value)
+    }
+
+    /// Converts `percent` of the maximum brightness to a raw value,
+    /// according to the [`scale`].
+    ///
+    /// The percentage is clamped to `0.0..=100.0`; a `NaN` is rejected.
+    ///
+    /// [`scale`]: #method.scale
+    fn percent_to_raw(&self, percent: f64) -> Result<i32> {
+        if percent.is_nan() {
+            return Err(Error::invalid_value(
+                attribute_path(self, BRIGHTNESS),
+                percent,
+                "a percentage",
+            ));
+        }
+        let max = self.max_brightness()?;
+        let fraction = self.scale().to_fraction(percent / 100.0);
+        Ok((fraction * f64::from(max)).round() as i32)
+    }
+
+    /// Converts a raw brightness `value` to a percentage of the maximum,
+    /// according to the [`scale`].
+    ///
+    /// [`scale`]: #method.scale
+    fn raw_to_percent(&self, value: i32) -> Result<f64> {
+        let max = self.max_brightness()?;
+        if max <= 0 {
+            return Err(Error::invalid_value(
+                attribute_path(self, MAX_BRIGHTNESS),
+                max,
+                "a positive value",
+            ));
+        }
+        let fraction = f64::from(value) / f64::from(max);
+        Ok(self.scale().from_fraction(fraction) * 100.0)
+    }
+
+    /// Changes the brightness by `delta`, clamping the result to
+    /// `min_brightness..=ceiling_brightness`.
+    ///
+    /// If the brightness is already below the floor, a step down leaves it
+    /// where it is instead of raising it, and likewise above the ceiling.
+    ///
+    /// The return value is the raw brightness that was written.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_lcd::{Backlight, Device, Step};
+    /// # use std::fs;
+    /// let path = std::env::temp_dir().join("rust-lcd-doc-step");
+    /// # fs::create_dir_all(&path).unwrap();
+    /// fs::write(path.join("max_brightness"), "200\n").unwrap();
+    /// fs::write(path.join("brightness"), "100\n").unwrap();
+    /// let dev = Device::new(&path);
+    /// assert_eq!(dev.step(Step::Percent(5.0)).unwrap(), 110);
+    /// assert_eq!(dev.step(Step::Raw(-30)).unwrap(), 80);
+    /// assert_eq!(dev.step(Step::Percent(-100.0)).unwrap(), 1);
+    /// # fs::remove_dir_all(&path).unwrap();
+    /// ```
+    fn step(&self, delta: Step) -> Result<i32> {
+        let value = self.step_target(delta)?;
+        // The target is only out of the limits if the current value already is.
+        if value < self.min_brightness()? || value > self.ceiling_brightness()? {
+            self.force_brightness(value)?;
+        } else {
+            self.set_brightness(value)?;
+        }
+        Ok(value)
+    }
+
+    /// Computes the raw brightness that [`step`] would write, without writing it.
+    ///
+    /// A nonzero percentage step always moves the brightness by at least one
+    /// raw unit, unless it is already at the limit, so that repeated small
+    /// steps cannot get stuck because of rounding.
+    ///
+    /// [`step`]: #method.step
+    fn step_target(&self, delta: Step) -> Result<i32> {
+        step::step_target(self, delta)
+    }
+
+    /// Fades the brightness from its current value to the raw `target`.
+    ///
+    /// Intermediate values are written following `curve`, at most once every
+    /// [`FADE_INTERVAL`], so that the whole fade lasts about `duration`.
+    /// The target is validated like in [`set_brightness`] before anything is written.
+    ///
+    /// The return value is the brightness reached, which is `target`.
+    ///
+    /// [`FADE_INTERVAL`]: constant.FADE_INTERVAL.html
+    /// [`set_brightness`]: #tymethod.set_brightness
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_lcd::{Backlight, Curve, Device};
+    /// # use std::fs;
+    /// use std::time::Duration;
+    /// let path = std::env::temp_dir().join("rust-lcd-doc-fade");
+    /// # fs::create_dir_all(&path).unwrap();
+    /// fs::write(path.join("max_brightness"), "100\n").unwrap();
+    /// fs::write(path.join("brightness"), "100\n").unwrap();
+    /// let dev = Device::new(&path);
+    /// let reached = dev.fade_to(10, Duration::from_millis(50), Curve::Exponential);
+    /// assert_eq!(reached.unwrap(), 10);
+    /// assert_eq!(dev.brightness().unwrap(), 10);
+    /// # fs::remove_dir_all(&path).unwrap();
+    /// ```
+    fn fade_to(&self, target: i32, duration: Duration, curve: Curve) -> Result<i32> {
+        self.fade_to_until(target, duration, curve, &AtomicBool::new(false))
+    }
+
+    /// Like [`fade_to`], but stops as soon as `stop` becomes `true`.
+    ///
+    /// This is meant to be used with a flag raised by a signal handler or by
+    /// another thread. When the fade is stopped, the brightness stays at the
+    /// last value written, which is returned.
+    ///
+    /// [`fade_to`]: #method.fade_to
+    fn fade_to_until(
+        &self,
+        target: i32,
+        duration: Duration,
+        curve: Curve,
+        stop: &AtomicBool,
+    ) -> Result<i32> {
+        fade::fade_to_until(self, target, duration, curve, stop)
+    }
+
+    /// Like [`fade_to_until`], but ignoring the floor and the ceiling: the
+    /// target only has to lie between 0 and [`max_brightness`].
+    ///
+    /// [`fade_to_until`]: #method.fade_to_until
+    /// [`max_brightness`]: #tymethod.max_brightness
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_lcd::{Backlight, Curve, DeviceBuilder, Level};
+    /// # use std::fs;
+    /// use std::sync::atomic::AtomicBool;
+    /// use std::time::Duration;
+    /// let path = std::env::temp_dir().join("rust-lcd-doc-force-fade");
+    /// # fs::create_dir_all(&path).unwrap();
+    /// fs::write(path.join("max_brightness"), "100\n").unwrap();
+    /// fs::write(path.join("brightness"), "50\n").unwrap();
+    /// let dev = DeviceBuilder::new().floor(Level::Raw(10)).build(&path);
+    /// let stop = AtomicBool::new(false);
+    /// let (duration, curve) = (Duration::from_millis(20), Curve::Linear);
+    /// assert!(dev.fade_to_until(0, duration, curve, &stop).is_err());
+    /// assert_eq!(dev.force_fade_to_until(0, duration, curve, &stop).unwrap(), 0);
+    /// assert_eq!(dev.brightness().unwrap(), 0);
+    /// # fs::remove_dir_all(&path).unwrap();
+    /// ```
+    fn force_fade_to_until(
+        &self,
+        target: i32,
+        duration: Duration,
+        curve: Curve,
+        stop: &AtomicBool,
+    ) -> Result<i32> {
+        fade::force_fade_to_until(self, target, duration, curve, stop)
+    }
+
+    /// Reads all the attributes of the backlight into a [`DeviceSnapshot`].
+    ///
+    /// [`DeviceSnapshot`]: struct.DeviceSnapshot.html
+    fn snapshot(&self) -> DeviceSnapshot {
+        snapshot::take(self)
+    }
+}
+
+impl<B: Backlight + ?Sized> Backlight for Box<B> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn path(&self) -> Option<&Path> {
+        (**self).path()
+    }
+
+    fn id(&self) -> Result<String> {
+        (**self).id()
+    }
+
+    fn backlight_type(&self) -> Result<BacklightType> {
+        (**self).backlight_type()
+    }
+
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        (**self).watched_paths()
+    }
+
+    fn power_state(&self) -> Result<PowerState> {
+        (**self).power_state()
+    }
+
+    fn set_power_state(&self, state: PowerState) -> Result<()> {
+        (**self).set_power_state(state)
+    }
+
+    fn brightness(&self) -> Result<i32> {
+        (**self).brightness()
+    }
+
+    fn actual_brightness(&self) -> Result<i32> {
+        (**self).actual_brightness()
+    }
+
+    fn max_brightness(&self) -> Result<i32> {
+        (**self).max_brightness()
+    }
+
+    fn min_brightness(&self) -> Result<i32> {
+        (**self).min_brightness()
+    }
+
+    fn ceiling_brightness(&self) -> Result<i32> {
+        (**self).ceiling_brightness()
+    }
+
+    fn set_brightness(&self, value: i32) -> Result<()> {
+        (**self).set_brightness(value)
+    }
+
+    fn force_brightness(&self, value: i32) -> Result<()> {
+        (**self).force_brightness(value)
+    }
+
+    fn scale(&self) -> Scale {
+        (**self).scale()
+    }
+
+    fn set_floor(&mut self, floor: Level) -> Result<()> {
+        (**self).set_floor(floor)
+    }
+
+    fn set_ceiling(&mut self, ceiling: Option<Level>) -> Result<()> {
+        (**self).set_ceiling(ceiling)
+    }
+
+    fn set_scale(&mut self, scale: Scale) -> Result<()> {
+        (**self).set_scale(scale)
+    }
+}
+
+impl Backlight for Device {
+    fn name(&self) -> &str {
+        Device::name(self)
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(Device::path(self))
+    }
+
+    fn id(&self) -> Result<String> {
+        self.stable_id()
+    }
+
+    fn backlight_type(&self) -> Result<BacklightType> {
+        Device::backlight_type(self)
+    }
+
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        vec![
+            self.brightness.clone(),
+            self.path.join(ACTUAL_BRIGHTNESS),
+            self.bl_power.clone(),
+        ]
+    }
+
+    fn power_state(&self) -> Result<PowerState> {
+        Device::power_state(self)
+    }
+
+    fn set_power_state(&self, state: PowerState) -> Result<()> {
+        Device::set_power_state(self, state)
+    }
+
+    fn brightness(&self) -> Result<i32> {
+        Device::brightness(self)
+    }
+
+    fn actual_brightness(&self) -> Result<i32> {
+        Device::actual_brightness(self)
+    }
+
+    fn max_brightness(&self) -> Result<i32> {
+        Device::max_brightness(self)
+    }
+
+    fn min_brightness(&self) -> Result<i32> {
+        Device::min_brightness(self)
+    }
+
+    fn ceiling_brightness(&self) -> Result<i32> {
+        Device::ceiling_brightness(self)
+    }
+
+    fn set_brightness(&self, value: i32) -> Result<()> {
+        Device::set_brightness(self, value)
+    }
+
+    fn force_brightness(&self, value: i32) -> Result<()> {
+        Device::force_brightness(self, value)
+    }
+
+    fn scale(&self) -> Scale {
+        Device::scale(self)
+    }
+
+    fn set_floor(&mut self, floor: Level) -> Result<()> {
+        Device::set_floor(self, floor);
+        Ok(())
+    }
+
+    fn set_ceiling(&mut self, ceiling: Option<Level>) -> Result<()> {
+        Device::set_ceiling(self, ceiling);
+        Ok(())
+    }
+
+    fn set_scale(&mut self, scale: Scale) -> Result<()> {
+        Device::set_scale(self, scale);
+        Ok(())
+    }
+}
+
+impl From<Device> for Box<dyn Backlight> {
+    fn from(device: Device) -> Self {
+        Box::new(device)
+    }
+}
+
+/// Returns the error of a backlight that does not support `setting`.
+fn unsupported<B: Backlight + ?Sized>(backlight: &B, setting: &'static str) -> Error {
+    Error::Unsupported {
+        path: backlight
+            .path()
+            .map_or_else(|| PathBuf::from(backlight.name()), Path::to_path_buf),
+        setting,
+    }
+}
+
+/// Returns the path of `attribute` for error messages,
+/// made up from the name for the backlights without a folder.
+pub(crate) fn attribute_path<B: Backlight + ?Sized>(backlight: &B, attribute: &str) -> PathBuf {
+    match backlight.path() {
+        Some(path) => path.join(attribute),
+        None => Path::new(backlight.name()).join(attribute),
+    }
+}
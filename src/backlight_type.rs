@@ -0,0 +1,79 @@
+//! Types of backlight devices.
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The type of a backlight device, as read from [`TYPE`].
+///
+/// The variants are ordered by the kernel's own preference:
+/// `Firmware > Platform > Raw`. Desktop environments prefer the native
+/// `Raw` devices instead, as [`preferred_device`] does.
+///
+/// [`TYPE`]: constant.TYPE.html
+/// [`preferred_device`]: fn.preferred_device.html
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::BacklightType;
+/// assert_eq!("firmware".parse(), Ok(BacklightType::Firmware));
+/// assert_eq!(BacklightType::Raw.to_string(), "raw");
+/// assert!(BacklightType::Firmware > BacklightType::Platform);
+/// assert!(BacklightType::Platform > BacklightType::Raw);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum BacklightType {
+    /// The backlight is controlled by writing directly to hardware registers.
+    Raw,
+    /// The backlight is controlled through a platform-specific interface.
+    Platform,
+    /// The backlight is controlled through a standard firmware interface.
+    Firmware,
+}
+
+impl BacklightType {
+    /// Returns the name of the type, as written by the kernel.
+    pub fn name(self) -> &'static str {
+        match self {
+            BacklightType::Raw => "raw",
+            BacklightType::Platform => "platform",
+            BacklightType::Firmware => "firmware",
+        }
+    }
+}
+
+impl fmt::Display for BacklightType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for BacklightType {
+    type Err = ParseBacklightTypeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "raw" => Ok(BacklightType::Raw),
+            "platform" => Ok(BacklightType::Platform),
+            "firmware" => Ok(BacklightType::Firmware),
+            _ => Err(ParseBacklightTypeError(s.trim().to_string())),
+        }
+    }
+}
+
+/// The error returned when a [`BacklightType`] cannot be parsed.
+///
+/// [`BacklightType`]: enum.BacklightType.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBacklightTypeError(String);
+
+impl fmt::Display for ParseBacklightTypeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid backlight type '{}'", self.0)
+    }
+}
+
+impl error::Error for ParseBacklightTypeError {}
@@ -0,0 +1,789 @@
+//! Configuration files with defaults and per-device settings.
+
+use crate::{Backlight, Curve, Error, Level, Result, Scale, Scene, Step};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The configuration file shared by all the users.
+pub const SYSTEM_CONFIG_PATH: &str = "/etc/rust-lcd.toml";
+
+/// Returns the configuration file of the user, `$XDG_CONFIG_HOME/rust-lcd/config.toml`
+/// or `~/.config/rust-lcd/config.toml`, if the home can be found.
+pub fn user_config_path() -> Option<PathBuf> {
+    env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".config")))
+        .map(|dir| dir.join("rust-lcd").join("config.toml"))
+}
+
+/// The settings of a device, each one optional.
+///
+/// The unset settings are taken from the next source in order of precedence,
+/// see [`Config`].
+///
+/// [`Config`]: struct.Config.html
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Settings {
+    /// The minimum brightness, see [`Backlight::set_floor`].
+    ///
+    /// [`Backlight::set_floor`]: trait.Backlight.html#method.set_floor
+    pub min: Option<Level>,
+    /// The maximum brightness, see [`Backlight::set_ceiling`].
+    ///
+    /// [`Backlight::set_ceiling`]: trait.Backlight.html#method.set_ceiling
+    pub max: Option<Level>,
+    /// The step of `brightness up`, negated for `brightness down`.
+    ///
+    /// In the files it is written without a sign, like a [`Level`].
+    ///
+    /// [`Level`]: enum.Level.html
+    pub step: Option<Step>,
+    /// The duration of the fades.
+    pub fade: Option<Duration>,
+    /// The curve of the fades.
+    pub curve: Option<Curve>,
+    /// The scale of the percentages.
+    pub scale: Option<Scale>,
+}
+
+impl Settings {
+    /// Overrides the settings with those set in `other`.
+    pub fn merge(&mut self, other: &Settings) {
+        self.min = other.min.or(self.min);
+        self.max = other.max.or(self.max);
+        self.step = other.step.or(self.step);
+        self.fade = other.fade.or(self.fade);
+        self.curve = other.curve.or(self.curve);
+        self.scale = other.scale.or(self.scale);
+    }
+
+    /// Sets the floor, the ceiling and the scale of `device`, when they are set.
+    ///
+    /// The error of a backlight that does not support one of them, like a
+    /// [`MockBacklight`], is returned.
+    ///
+    /// [`MockBacklight`]: struct.MockBacklight.html
+    pub fn apply<B: Backlight + ?Sized>(&self, device: &mut B) -> Result<()> {
+        if let Some(min) = self.min {
+            device.set_floor(min)?;
+        }
+        if let Some(max) = self.max {
+            device.set_ceiling(Some(max))?;
+        }
+        if let Some(scale) = self.scale {
+            device.set_scale(scale)?;
+        }
+        Ok(())
+    }
+}
+
+/// The configuration of `rust-lcd`, read from TOML files.
+///
+/// A file may pin the devices to touch when none is selected explicitly,
+/// set defaults for all the devices, settings for single devices, and
+/// [scenes], designated by their name or by their stable path, as returned
+/// by [`Device::stable_id`] (with or without `/sys/` in front):
+///
+/// ```toml
+/// devices = ["intel_backlight", "dell::kbd_backlight"]
+///
+/// [defaults]
+/// step = "5%"     # a percentage or a raw value
+/// fade = 200      # milliseconds
+/// curve = "ease-in-out"
+/// scale = "log"
+///
+/// [device.intel_backlight]
+/// min = "5%"      # a percentage or a raw value
+/// max = "90%"
+///
+/// [device."devices/platform/dell-laptop/leds/dell::kbd_backlight"]
+/// step = 1
+///
+/// [scene.movie]
+/// fade = 1000     # optional, for every device at once
+/// curve = "exponential"
+///
+/// [scene.movie.devices]
+/// intel_backlight = "20%"             # a level, which turns the device on
+/// "dell::kbd_backlight" = "off"       # a power state
+/// acpi_video0 = "0 normal"            # or both
+/// ```
+///
+/// The settings of a device are, from the lowest to the highest precedence:
+/// the defaults, then the sections matching the device, in the order they
+/// appear. When configurations are [merged](#method.merge), the defaults of
+/// all the files come before the sections of all the files.
+///
+/// Only tables, dotted keys, single-line strings, decimal integers and arrays
+/// are understood; the rest of TOML, like booleans, floats or inline tables,
+/// is reported as [`Error::Unsupported`].
+///
+/// [scenes]: struct.Scene.html
+/// [`Device::stable_id`]: struct.Device.html#method.stable_id
+/// [`Error::Unsupported`]: enum.Error.html#variant.Unsupported
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{Config, Device, Level, MockBacklight, Step};
+/// # use std::fs;
+/// let root = std::env::temp_dir().join("rust-lcd-doc-config");
+/// # fs::create_dir_all(&root).unwrap();
+/// fs::write(
+///     root.join("config.toml"),
+///     "devices = ['intel_backlight']\n\
+///      [defaults]\n\
+///      step = '5%'\n\
+///      [device.intel_backlight]\n\
+///      max = \"90%\" # never dazzle\n\
+///      [scene.night]\n\
+///      fade = 500\n\
+///      devices.intel_backlight = '5%'\n",
+/// )
+/// .unwrap();
+/// let config = Config::load(root.join("config.toml")).unwrap();
+///
+/// let panel = MockBacklight::new("intel_backlight", 100);
+/// assert!(config.is_pinned(&panel));
+/// assert!(!config.is_pinned(&MockBacklight::new("acpi_video0", 100)));
+/// let settings = config.settings(&panel);
+/// assert_eq!(settings.step, Some(Step::Percent(5.0)));
+/// assert_eq!(settings.max, Some(Level::Percent(90.0)));
+/// let night = config.scene("night").unwrap();
+/// assert_eq!(night.target(&panel).unwrap().brightness, Some(Level::Percent(5.0)));
+///
+/// let mut dev = Device::new(root.join("intel_backlight"));
+/// settings.apply(&mut dev).unwrap();
+/// assert_eq!(dev.ceiling(), Some(Level::Percent(90.0)));
+/// # fs::remove_dir_all(&root).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Config {
+    /// The names or stable paths of the devices to touch when none is
+    /// selected explicitly; all of them if empty.
+    pub devices: Vec<String>,
+    /// The settings of all the devices.
+    pub defaults: Settings,
+    /// The settings of single devices, keyed by name or stable path.
+    pub device: Vec<(String, Settings)>,
+    /// The scenes, by name.
+    pub scenes: Vec<Scene>,
+}
+
+impl Config {
+    /// Loads the configuration from the TOML file at `path`.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        Self::parse(path, &content)
+    }
+
+    /// Loads [`SYSTEM_CONFIG_PATH`], then the [`user_config_path`] on top of it,
+    /// skipping the files that do not exist.
+    ///
+    /// Since the file of the user is read, a setuid program must call this
+    /// only once the privileges are dropped.
+    ///
+    /// [`SYSTEM_CONFIG_PATH`]: constant.SYSTEM_CONFIG_PATH.html
+    /// [`user_config_path`]: fn.user_config_path.html
+    pub fn load_default() -> Result<Self> {
+        let mut config = Config::default();
+        let paths = Some(PathBuf::from(SYSTEM_CONFIG_PATH))
+            .into_iter()
+            .chain(user_config_path());
+        for path in paths {
+            match Self::load(&path) {
+                Ok(file) => config.merge(file),
+                Err(Error::NotFound { .. }) => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Adds `other` on top of the configuration.
+    ///
+    /// The pinned devices of `other` replace the current ones if there are
+    /// any, its defaults override the current ones, its device sections
+    /// come after the current ones, and its scenes replace those of the
+    /// same name.
+    pub fn merge(&mut self, other: Config) {
+        if !other.devices.is_empty() {
+            self.devices = other.devices;
+        }
+        self.defaults.merge(&other.defaults);
+        self.device.extend(other.device);
+        for scene in other.scenes {
+            self.scenes.retain(|s| s.name != scene.name);
+            self.scenes.push(scene);
+        }
+    }
+
+    /// Returns the scene named `name`.
+    pub fn scene(&self, name: &str) -> Option<&Scene> {
+        self.scenes.iter().find(|scene| scene.name == name)
+    }
+
+    /// Returns `true` if `device` is among the pinned devices,
+    /// or if no device is pinned.
+    pub fn is_pinned<B: Backlight + ?Sized>(&self, device: &B) -> bool {
+        self.devices.is_empty() || self.devices.iter().any(|key| matches(key, device))
+    }
+
+    /// Returns the settings of `device`: the defaults, overridden by the
+    /// sections matching it.
+    pub fn settings<B: Backlight + ?Sized>(&self, device: &B) -> Settings {
+        let mut settings = self.defaults.clone();
+        for (key, section) in &self.device {
+            if matches(key, device) {
+                settings.merge(section);
+            }
+        }
+        settings
+    }
+
+    /// Returns the scene named `name`, adding it if there is none.
+    fn scene_mut(&mut self, name: &str) -> &mut Scene {
+        match self.scenes.iter().position(|scene| scene.name == name) {
+            Some(i) => &mut self.scenes[i],
+            None => {
+                self.scenes.push(Scene::new(name));
+                self.scenes.last_mut().unwrap()
+            }
+        }
+    }
+
+    fn parse(path: &Path, content: &str) -> Result<Self> {
+        let lines: Vec<&str> = content.lines().collect();
+        let line = |number: usize| lines.get(number).copied().unwrap_or("");
+        let mut config = Config::default();
+        let items = Parser::new(content).parse().map_err(|e| match e {
+            ParseError::Expected(number, expected) => Error::parse(path, line(number), expected),
+            ParseError::Unsupported(_, setting) => Error::Unsupported {
+                path: path.to_path_buf(),
+                setting,
+            },
+        })?;
+        for item in items {
+            let number = item.line;
+            let error = |expected| Error::parse(path, line(number), expected);
+            let (table, key) = item.key.split_at(item.key.len() - 1);
+            let settings = match table {
+                [] if key[0] == "devices" => {
+                    config.devices = match item.value {
+                        Value::Array(values) => values
+                            .into_iter()
+                            .map(|value| match value {
+                                Value::String(name) => Ok(name),
+                                _ => Err(error("an array of device names")),
+                            })
+                            .collect::<Result<_>>()?,
+                        _ => return Err(error("an array of device names")),
+                    };
+                    continue;
+                }
+                [defaults] if defaults == "defaults" => &mut config.defaults,
+                [scene, name] if scene == "scene" => {
+                    let scene = config.scene_mut(name);
+                    match (key[0].as_str(), item.value) {
+                        ("fade", Value::Integer(ms)) if ms >= 0 => {
+                            scene.fade = Some(Duration::from_millis(ms as u64))
+                        }
+                        ("fade", _) => return Err(error("a duration in milliseconds")),
+                        ("curve", Value::String(curve)) => {
+                            scene.curve = Some(curve.parse().map_err(|_| error("a fade curve"))?)
+                        }
+                        ("curve", _) => return Err(error("a string")),
+                        _ => return Err(error("fade, curve or the [scene.NAME.devices] table")),
+                    }
+                    continue;
+                }
+                [scene, name, devices] if scene == "scene" && devices == "devices" => {
+                    let scene = config.scene_mut(name);
+                    match (key[0].as_str(), item.value) {
+                        (device, Value::String(target)) => {
+                            let target = target.parse().map_err(|_| error("a scene target"))?;
+                            scene.devices.push((device.to_string(), target));
+                        }
+                        (device, Value::Integer(raw)) if raw >= 0 => {
+                            let target = raw.to_string().parse().unwrap();
+                            scene.devices.push((device.to_string(), target));
+                        }
+                        _ => return Err(error("a scene target")),
+                    }
+                    continue;
+                }
+                [device, name] if device == "device" => {
+                    if config.device.last().map(|(last, _)| last) != Some(name) {
+                        config.device.push((name.clone(), Settings::default()));
+                    }
+                    &mut config.device.last_mut().unwrap().1
+                }
+                _ => {
+                    return Err(error(
+                        "devices, [defaults], [device.NAME], [scene.NAME] or [scene.NAME.devices]",
+                    ))
+                }
+            };
+            match (key[0].as_str(), item.value) {
+                ("min", Value::Integer(raw)) if raw >= 0 => settings.min = Some(Level::Raw(raw)),
+                ("min", Value::String(level)) => {
+                    settings.min = Some(level.parse().map_err(|_| error("a brightness level"))?)
+                }
+                ("max", Value::Integer(raw)) if raw >= 0 => settings.max = Some(Level::Raw(raw)),
+                ("max", Value::String(level)) => {
+                    settings.max = Some(level.parse().map_err(|_| error("a brightness level"))?)
+                }
+                ("step", Value::Integer(raw)) if raw >= 0 => settings.step = Some(Step::Raw(raw)),
+                ("step", Value::String(step)) => {
+                    settings.step = match step.parse().map_err(|_| error("a step"))? {
+                        Level::Raw(raw) => Some(Step::Raw(raw)),
+                        Level::Percent(percent) => Some(Step::Percent(percent)),
+                    }
+                }
+                ("fade", Value::Integer(ms)) if ms >= 0 => {
+                    settings.fade = Some(Duration::from_millis(ms as u64))
+                }
+                ("curve", Value::String(curve)) => {
+                    settings.curve = Some(curve.parse().map_err(|_| error("a fade curve"))?)
+                }
+                ("scale", Value::String(scale)) => {
+                    settings.scale = Some(scale.parse().map_err(|_| error("a scale"))?)
+                }
+                ("min", _) | ("max", _) => return Err(error("a brightness level")),
+                ("step", _) => return Err(error("a step")),
+                ("fade", _) => return Err(error("a duration in milliseconds")),
+                ("curve", _) | ("scale", _) => return Err(error("a string")),
+                _ => return Err(error("min, max, step, fade, curve or scale")),
+            }
+        }
+        Ok(config)
+    }
+}
+
+/// Returns `true` if `key` is the name or the stable path of `device`.
+pub(crate) fn matches<B: Backlight + ?Sized>(key: &str, device: &B) -> bool {
+    key == device.name()
+        || device
+            .id()
+            .is_ok_and(|id| id == key.strip_prefix("/sys/").unwrap_or(key))
+}
+
+/// A value of the subset of TOML understood by [`Parser`].
+#[derive(Debug, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i32),
+    Array(Vec<Value>),
+}
+
+/// A key-value pair, with the full key including the table.
+#[derive(Debug)]
+struct Item {
+    key: Vec<String>,
+    value: Value,
+    /// The line of the pair, counting from 0.
+    line: usize,
+}
+
+/// The error of the parser, with the line counting from 0.
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    /// The content does not follow TOML, and this was expected instead.
+    Expected(usize, &'static str),
+    /// The content is TOML, but outside the subset understood by the parser.
+    Unsupported(usize, &'static str),
+}
+
+/// A parser of the subset of TOML needed by the configuration: tables,
+/// dotted keys, single-line strings, decimal integers and arrays.
+struct Parser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    line: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(content: &'a str) -> Self {
+        Parser {
+            chars: content.chars().peekable(),
+            line: 0,
+        }
+    }
+
+    fn parse(mut self) -> std::result::Result<Vec<Item>, ParseError> {
+        let mut items = Vec::new();
+        let mut table = Vec::new();
+        loop {
+            self.skip_blank_lines();
+            match self.chars.peek() {
+                None => return Ok(items),
+                Some('[') => {
+                    self.chars.next();
+                    if self.chars.peek() == Some(&'[') {
+                        return Err(self.unsupported("TOML arrays of tables"));
+                    }
+                    self.skip_spaces();
+                    table = self.parse_key()?;
+                    self.expect(']', "a closing ']'")?;
+                }
+                Some(_) => {
+                    let line = self.line;
+                    let mut key = table.clone();
+                    key.extend(self.parse_key()?);
+                    self.expect('=', "'='")?;
+                    self.skip_spaces();
+                    let value = self.parse_value()?;
+                    items.push(Item { key, value, line });
+                }
+            }
+            self.skip_spaces();
+            self.skip_comment();
+            match self.chars.next() {
+                None | Some('\n') => self.line += 1,
+                Some('\r') if self.chars.next_if_eq(&'\n').is_some() => self.line += 1,
+                Some(_) => return Err(self.error("the end of the line")),
+            }
+        }
+    }
+
+    fn error(&self, expected: &'static str) -> ParseError {
+        ParseError::Expected(self.line, expected)
+    }
+
+    fn unsupported(&self, setting: &'static str) -> ParseError {
+        ParseError::Unsupported(self.line, setting)
+    }
+
+    fn expect(&mut self, c: char, expected: &'static str) -> std::result::Result<(), ParseError> {
+        self.skip_spaces();
+        match self.chars.next_if_eq(&c) {
+            Some(_) => Ok(()),
+            None => Err(self.error(expected)),
+        }
+    }
+
+    fn skip_spaces(&mut self) {
+        while self.chars.next_if(|&c| c == ' ' || c == '\t').is_some() {}
+    }
+
+    fn skip_comment(&mut self) {
+        if self.chars.peek() == Some(&'#') {
+            while self.chars.next_if(|&c| c != '\n').is_some() {}
+        }
+    }
+
+    /// Skips spaces, comments and newlines, as found between lines or array elements.
+    fn skip_blank_lines(&mut self) {
+        loop {
+            self.skip_spaces();
+            self.skip_comment();
+            match self.chars.peek() {
+                Some('\n') => self.line += 1,
+                Some('\r') => {}
+                _ => return,
+            }
+            self.chars.next();
+        }
+    }
+
+    /// Parses a dotted key made of bare and quoted parts.
+    fn parse_key(&mut self) -> std::result::Result<Vec<String>, ParseError> {
+        let mut key = Vec::new();
+        loop {
+            self.skip_spaces();
+            let part = match self.chars.peek() {
+                Some('"') | Some('\'') => self.parse_string()?,
+                _ => {
+                    let mut part = String::new();
+                    while let Some(c) = self
+                        .chars
+                        .next_if(|&c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+                    {
+                        part.push(c);
+                    }
+                    if part.is_empty() {
+                        return Err(self.error("a key"));
+                    }
+                    part
+                }
+            };
+            key.push(part);
+            self.skip_spaces();
+            if self.chars.next_if_eq(&'.').is_none() {
+                return Ok(key);
+            }
+        }
+    }
+
+    fn parse_value(&mut self) -> std::result::Result<Value, ParseError> {
+        match self.chars.peek() {
+            Some('"') | Some('\'') => self.parse_string().map(Value::String),
+            Some('{') => Err(self.unsupported("TOML inline tables")),
+            Some('[') => {
+                self.chars.next();
+                let mut values = Vec::new();
+                loop {
+                    self.skip_blank_lines();
+                    if self.chars.next_if_eq(&']').is_some() {
+                        return Ok(Value::Array(values));
+                    }
+                    values.push(self.parse_value()?);
+                    self.skip_blank_lines();
+                    if self.chars.next_if_eq(&',').is_none() {
+                        self.skip_blank_lines();
+                        return match self.chars.next() {
+                            Some(']') => Ok(Value::Array(values)),
+                            _ => Err(self.error("',' or ']'")),
+                        };
+                    }
+                }
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = self
+                    .chars
+                    .next_if(|&c| c.is_ascii_alphanumeric() || c == '_' || c == '+' || c == '-')
+                {
+                    word.push(c);
+                }
+                if let Ok(value) = word.replace('_', "").parse() {
+                    return match self.chars.peek() {
+                        Some('.') => Err(self.unsupported("TOML floats")),
+                        Some(':') => Err(self.unsupported("TOML dates and times")),
+                        _ => Ok(Value::Integer(value)),
+                    };
+                }
+                let digits = word.trim_start_matches(['+', '-']);
+                let is_number = digits.starts_with(|c: char| c.is_ascii_digit());
+                Err(match digits {
+                    "true" | "false" => self.unsupported("TOML booleans"),
+                    "inf" | "nan" => self.unsupported("TOML floats"),
+                    _ if ["0x", "0o", "0b"].iter().any(|p| digits.starts_with(p)) => {
+                        self.unsupported("TOML hexadecimal, octal and binary integers")
+                    }
+                    _ if is_number && digits.contains(['e', 'E']) => {
+                        self.unsupported("TOML floats")
+                    }
+                    _ if is_number && digits.contains('-') => {
+                        self.unsupported("TOML dates and times")
+                    }
+                    _ => self.error("a string, an integer or an array"),
+                })
+            }
+        }
+    }
+
+    /// Parses a basic string, with escapes, or a literal string.
+    fn parse_string(&mut self) -> std::result::Result<String, ParseError> {
+        let mut ahead = self.chars.clone();
+        let quote = ahead.next();
+        if ahead.next() == quote && ahead.next() == quote {
+            return Err(self.unsupported("TOML multi-line strings"));
+        }
+        let quote = self.chars.next();
+        let mut string = String::new();
+        loop {
+            let c = match self.chars.next() {
+                None | Some('\n') => return Err(self.error("a closing quote")),
+                Some(c) if Some(c) == quote => return Ok(string),
+                Some(c) => c,
+            };
+            if c != '\\' || quote == Some('\'') {
+                string.push(c);
+                continue;
+            }
+            let escaped = match self.chars.next() {
+                Some('"') => '"',
+                Some('\\') => '\\',
+                Some('n') => '\n',
+                Some('t') => '\t',
+                Some('r') => '\r',
+                Some(u @ 'u') | Some(u @ 'U') => {
+                    let len = if u == 'u' { 4 } else { 8 };
+                    let hex: String = (0..len).filter_map(|_| self.chars.next()).collect();
+                    u32::from_str_radix(&hex, 16)
+                        .ok()
+                        .and_then(char::from_u32)
+                        .ok_or_else(|| self.error("a unicode escape"))?
+                }
+                _ => return Err(self.error("a valid escape")),
+            };
+            string.push(escaped);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses `content` into its dotted keys and values.
+    fn parse(content: &str) -> std::result::Result<Vec<(String, Value)>, ParseError> {
+        let items = Parser::new(content).parse()?;
+        Ok(items
+            .into_iter()
+            .map(|item| (item.key.join("."), item.value))
+            .collect())
+    }
+
+    fn string(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn accepts_tables_and_keys() {
+        let content = "a = 1\n[defaults]\nb = 2\n[ device . \"x.y\" ]\n'c'.d-e_f = 3\n";
+        assert_eq!(
+            parse(content).unwrap(),
+            [
+                ("a".to_string(), Value::Integer(1)),
+                ("defaults.b".to_string(), Value::Integer(2)),
+                ("device.x.y.c.d-e_f".to_string(), Value::Integer(3)),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_strings() {
+        let content = r#"a = "q\"\\\n\t\u00e9\U0001F600"
+b = 'C:\path'
+c = ""
+"#;
+        assert_eq!(
+            parse(content).unwrap(),
+            [
+                ("a".to_string(), string("q\"\\\n\t\u{e9}\u{1F600}")),
+                ("b".to_string(), string("C:\\path")),
+                ("c".to_string(), string("")),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_integers() {
+        let content = "a = 1_000\nb = -3\nc = +4\n";
+        assert_eq!(
+            parse(content).unwrap(),
+            [
+                ("a".to_string(), Value::Integer(1000)),
+                ("b".to_string(), Value::Integer(-3)),
+                ("c".to_string(), Value::Integer(4)),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_arrays() {
+        let content = "a = []\nb = [ \"x\", [1],\n  2, # a comment\n]\n";
+        assert_eq!(
+            parse(content).unwrap(),
+            [
+                ("a".to_string(), Value::Array(vec![])),
+                (
+                    "b".to_string(),
+                    Value::Array(vec![
+                        string("x"),
+                        Value::Array(vec![Value::Integer(1)]),
+                        Value::Integer(2),
+                    ])
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_comments_blank_lines_and_crlf() {
+        let content = "# a comment\r\n\r\n  a = 1 # another\r\n\n[t] # a table\nb = 2";
+        let items = Parser::new(content).parse().unwrap();
+        let lines: Vec<usize> = items.iter().map(|item| item.line).collect();
+        assert_eq!(lines, [2, 5]);
+    }
+
+    #[test]
+    fn rejects_invalid_syntax() {
+        let cases = [
+            ("a 1", ParseError::Expected(0, "'='")),
+            ("= 1", ParseError::Expected(0, "a key")),
+            ("\n[t", ParseError::Expected(1, "a closing ']'")),
+            ("a = 1 2", ParseError::Expected(0, "the end of the line")),
+            ("a = \"x", ParseError::Expected(0, "a closing quote")),
+            ("a = \"\\q\"", ParseError::Expected(0, "a valid escape")),
+            ("a = \"\\uzz\"", ParseError::Expected(0, "a unicode escape")),
+            ("a = [1 2]", ParseError::Expected(0, "',' or ']'")),
+            (
+                "a = x",
+                ParseError::Expected(0, "a string, an integer or an array"),
+            ),
+            (
+                "a = 3000000000",
+                ParseError::Expected(0, "a string, an integer or an array"),
+            ),
+        ];
+        for (content, error) in &cases {
+            assert_eq!(parse(content).unwrap_err(), *error, "{}", content);
+        }
+    }
+
+    #[test]
+    fn reports_unsupported_toml() {
+        let cases = [
+            ("a = true", "TOML booleans"),
+            ("a = false", "TOML booleans"),
+            ("a = 1.5", "TOML floats"),
+            ("a = 1e3", "TOML floats"),
+            ("a = -inf", "TOML floats"),
+            ("a = nan", "TOML floats"),
+            ("a = 0x1f", "TOML hexadecimal, octal and binary integers"),
+            ("a = 0b101", "TOML hexadecimal, octal and binary integers"),
+            ("a = 1979-05-27", "TOML dates and times"),
+            ("a = 07:32:00", "TOML dates and times"),
+            ("a = { b = 1 }", "TOML inline tables"),
+            ("a = \"\"\"\nb\"\"\"", "TOML multi-line strings"),
+            ("a = \'\'\'b\'\'\'", "TOML multi-line strings"),
+            ("[[a]]", "TOML arrays of tables"),
+        ];
+        for (content, setting) in &cases {
+            let error = ParseError::Unsupported(0, setting);
+            assert_eq!(parse(content).unwrap_err(), error, "{}", content);
+        }
+    }
+
+    #[test]
+    fn keeps_the_scene_devices_apart() {
+        let path = Path::new("config.toml");
+        let content = "[scene.a]\nfade = 100\n[scene.a.devices]\nfade = '20%'\ncurve = 'off'\n";
+        let config = Config::parse(path, content).unwrap();
+        let scene = config.scene("a").unwrap();
+        assert_eq!(scene.fade, Some(Duration::from_millis(100)));
+        let keys: Vec<&str> = scene.devices.iter().map(|(key, _)| &key[..]).collect();
+        assert_eq!(keys, ["fade", "curve"]);
+
+        match Config::parse(path, "[scene.a]\nfade = '20%'\n") {
+            Err(Error::Parse { expected, .. }) => {
+                assert_eq!(expected, "a duration in milliseconds")
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+        match Config::parse(path, "[scene.a]\nintel_backlight = '20%'\n") {
+            Err(Error::Parse { expected, .. }) => {
+                assert_eq!(expected, "fade, curve or the [scene.NAME.devices] table")
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+
+    #[test]
+    fn reports_unsupported_toml_in_files() {
+        let path = Path::new("config.toml");
+        match Config::parse(path, "[defaults]\nfade = 0.5\n") {
+            Err(Error::Unsupported { setting, .. }) => assert_eq!(setting, "TOML floats"),
+            other => panic!("unexpected result {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,595 @@
+//! A privileged daemon controlling the devices on behalf of unprivileged clients.
+//!
+//! The daemon listens on a Unix socket and speaks a line-based protocol.
+//! Each request is a single line made of whitespace-separated words, and
+//! each response is a single line starting with `ok` or `error`:
+//!
+//! | Request                         | Response                          |
+//! |---------------------------------|-----------------------------------|
+//! | `list`                          | `ok NAME...`                      |
+//! | `get NAME`                      | `ok BRIGHTNESS MAX_BRIGHTNESS POWER` |
+//! | `on NAME`, `off NAME`           | `ok`                              |
+//! | `toggle NAME`                   | `ok POWER`                        |
+//! | `set NAME POWER`                | `ok`                              |
+//! | `brightness NAME RAW`           | `ok`                              |
+//! | `force NAME RAW`                | `ok`                              |
+//! | `step NAME STEP`                | `ok RAW`                          |
+//! | `fade NAME RAW MS CURVE [force]` | `ok RAW`                         |
+//!
+//! `POWER` is a [`PowerState`] name, `STEP` a [`Step`] like `+5%` and
+//! `CURVE` a [`Curve`] name. Errors are reported as `error MESSAGE`. The
+//! folders are scanned again only on `list`, so a device that appeared
+//! since is unknown to the other requests until a client lists the devices.
+//!
+//! Any user may connect, but only root and the members of the admin group
+//! of the server, as told by the kernel, may `force` the brightness, or
+//! fade it with `force`, beyond the limits; the others may only bring back
+//! a brightness already out of the limits, as a step does. A request is at
+//! most [`MAX_REQUEST_LEN`] bytes long, at most [`MAX_CLIENTS`] clients are
+//! served at once, and a fade lasts at most [`MAX_FADE`].
+//!
+//! [`MAX_REQUEST_LEN`]: constant.MAX_REQUEST_LEN.html
+//! [`MAX_CLIENTS`]: constant.MAX_CLIENTS.html
+//! [`MAX_FADE`]: constant.MAX_FADE.html
+//!
+//! [`PowerState`]: ../enum.PowerState.html
+//! [`Step`]: ../enum.Step.html
+//! [`Curve`]: ../enum.Curve.html
+
+use crate::{
+    sys, Backlight, Curve, DeviceBuilder, Error, LedIter, PowerState, Result, Step, TryDeviceIter,
+};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// The default path of the socket of the daemon.
+pub const SOCKET_PATH: &str = "/run/rust-lcd.sock";
+
+/// The maximum length of a request, newline included; the connection of a
+/// client sending a longer one is closed.
+pub const MAX_REQUEST_LEN: usize = 256;
+
+/// The maximum number of clients served at once; the others are turned away.
+pub const MAX_CLIENTS: usize = 16;
+
+/// The maximum duration of a fade.
+pub const MAX_FADE: Duration = Duration::from_secs(60);
+
+/// The daemon side of the protocol.
+///
+/// The server holds the devices found in a folder, and optionally the
+/// keyboard backlights found in another one, and executes the requests
+/// of the clients connected to its socket, each one in its own thread.
+/// The fades in progress stop along with the server, and the socket is
+/// removed when the server is dropped.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::daemon::{Client, Server};
+/// # use rust_lcd::DeviceBuilder;
+/// # use std::fs;
+/// let root = std::env::temp_dir().join("rust-lcd-doc-daemon");
+/// # let _ = fs::remove_dir_all(&root);
+/// fs::create_dir_all(root.join("intel_backlight")).unwrap();
+/// fs::write(root.join("intel_backlight").join("bl_power"), "0\n").unwrap();
+/// let socket = root.join("socket");
+/// let server = Server::bind(&socket, &root, DeviceBuilder::new()).unwrap();
+/// std::thread::spawn(move || server.serve());
+///
+/// let mut client = Client::connect(&socket).unwrap();
+/// assert_eq!(client.list().unwrap(), vec!["intel_backlight".to_string()]);
+/// client.power_off("intel_backlight").unwrap();
+/// let state = fs::read_to_string(root.join("intel_backlight").join("bl_power")).unwrap();
+/// assert_eq!(state, "1");
+/// assert!(client.request(&"get ".repeat(100)).is_err());
+/// # fs::remove_dir_all(&root).unwrap();
+/// ```
+pub struct Server {
+    socket: PathBuf,
+    listener: UnixListener,
+    dir: PathBuf,
+    builder: DeviceBuilder,
+    led_dir: Option<PathBuf>,
+    admin_group: Option<u32>,
+    devices: Arc<Mutex<Devices>>,
+    clients: Arc<AtomicUsize>,
+    stopping: Arc<AtomicBool>,
+}
+
+/// The devices served, by name.
+type Devices = BTreeMap<String, Arc<dyn Backlight>>;
+
+impl Server {
+    /// Binds the socket at `socket`, serving the devices found in `dir`.
+    ///
+    /// A stale socket left by a crashed daemon is replaced, while a socket on
+    /// which another daemon is still listening results in an error.
+    /// The socket is made accessible to every user, see the
+    /// [module documentation](index.html) for what they may do.
+    pub fn bind<P, Q>(socket: P, dir: Q, builder: DeviceBuilder) -> Result<Self>
+    where
+        P: AsRef<Path>,
+        Q: AsRef<Path>,
+    {
+        let socket = socket.as_ref();
+        if socket.exists() {
+            if UnixStream::connect(socket).is_ok() {
+                let e = io::Error::new(io::ErrorKind::AddrInUse, "a daemon is already running");
+                return Err(Error::io(socket, e));
+            }
+            fs::remove_file(socket).map_err(|e| Error::io(socket, e))?;
+        }
+        let listener = UnixListener::bind(socket).map_err(|e| Error::io(socket, e))?;
+        fs::set_permissions(socket, fs::Permissions::from_mode(0o666))
+            .map_err(|e| Error::io(socket, e))?;
+        let server = Self {
+            socket: socket.to_path_buf(),
+            listener,
+            dir: dir.as_ref().to_path_buf(),
+            builder,
+            led_dir: None,
+            admin_group: None,
+            devices: Arc::new(Mutex::new(BTreeMap::new())),
+            clients: Arc::new(AtomicUsize::new(0)),
+            stopping: Arc::new(AtomicBool::new(false)),
+        };
+        server.rescan()?;
+        Ok(server)
+    }
+
+    /// Also serves the keyboard backlights found in `dir`, usually [`LEDS_PATH`].
+    ///
+    /// [`LEDS_PATH`]: ../constant.LEDS_PATH.html
+    pub fn with_leds<P: AsRef<Path>>(mut self, dir: P) -> Result<Self> {
+        self.led_dir = Some(dir.as_ref().to_path_buf());
+        self.rescan()?;
+        Ok(self)
+    }
+
+    /// Also lets the members of the group `gid` force the brightness
+    /// beyond the limits, which only root may do otherwise.
+    pub fn admin_group(mut self, gid: u32) -> Self {
+        self.admin_group = Some(gid);
+        self
+    }
+
+    /// Serves the clients forever.
+    pub fn serve(&self) -> Result<()> {
+        self.serve_until(&AtomicBool::new(false))
+    }
+
+    /// Serves the clients until `stop` becomes `true`.
+    ///
+    /// The flag is checked at least every 100 milliseconds.
+    pub fn serve_until(&self, stop: &AtomicBool) -> Result<()> {
+        let result = self.accept_until(stop);
+        self.stopping.store(true, Ordering::SeqCst);
+        result
+    }
+
+    fn accept_until(&self, stop: &AtomicBool) -> Result<()> {
+        while !stop.load(Ordering::SeqCst) {
+            let ready = sys::wait_readable(&self.listener, Duration::from_millis(100))
+                .map_err(|e| Error::io(&self.socket, e))?;
+            if !ready {
+                continue;
+            }
+            let mut stream = match self.listener.accept() {
+                Ok((stream, _)) => stream,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(Error::io(&self.socket, e)),
+            };
+            if self.clients.fetch_add(1, Ordering::SeqCst) >= MAX_CLIENTS {
+                self.clients.fetch_sub(1, Ordering::SeqCst);
+                let _ = writeln!(stream, "error too many clients");
+                continue;
+            }
+            let mut handler = self.handler();
+            handler.may_force = match sys::peer_credentials(&stream) {
+                Ok(peer) => {
+                    peer.uid == 0
+                        || self
+                            .admin_group
+                            .is_some_and(|gid| peer.groups.contains(&gid))
+                }
+                Err(_) => false,
+            };
+            thread::spawn(move || {
+                handler.serve_client(stream);
+                handler.clients.fetch_sub(1, Ordering::SeqCst);
+            });
+        }
+        Ok(())
+    }
+
+    fn handler(&self) -> Handler {
+        Handler {
+            dir: self.dir.clone(),
+            builder: self.builder.clone(),
+            led_dir: self.led_dir.clone(),
+            devices: Arc::clone(&self.devices),
+            clients: Arc::clone(&self.clients),
+            stopping: Arc::clone(&self.stopping),
+            may_force: false,
+        }
+    }
+
+    fn rescan(&self) -> Result<()> {
+        self.handler().rescan()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stopping.store(true, Ordering::SeqCst);
+        let _ = fs::remove_file(&self.socket);
+    }
+}
+
+/// Executes the requests of a single client.
+struct Handler {
+    dir: PathBuf,
+    builder: DeviceBuilder,
+    led_dir: Option<PathBuf>,
+    devices: Arc<Mutex<Devices>>,
+    clients: Arc<AtomicUsize>,
+    stopping: Arc<AtomicBool>,
+    /// Whether the client may go beyond the limits of the devices.
+    may_force: bool,
+}
+
+impl Handler {
+    fn serve_client(&self, stream: UnixStream) {
+        let mut writer = match stream.try_clone() {
+            Ok(writer) => writer,
+            Err(_) => return,
+        };
+        let mut reader = BufReader::new(stream);
+        loop {
+            let mut line = String::new();
+            match (&mut reader)
+                .take(MAX_REQUEST_LEN as u64)
+                .read_line(&mut line)
+            {
+                Ok(0) | Err(_) => return,
+                Ok(len) if len == MAX_REQUEST_LEN && !line.ends_with('\n') => {
+                    let _ = writeln!(writer, "error request too long");
+                    return;
+                }
+                Ok(_) => {}
+            }
+            let response = if self.stopping.load(Ordering::SeqCst) {
+                "error the daemon is stopping".to_string()
+            } else {
+                self.handle(line.trim_end())
+            };
+            if writeln!(writer, "{}", response).is_err() {
+                return;
+            }
+        }
+    }
+
+    fn rescan(&self) -> Result<()> {
+        let mut devices = Devices::new();
+        for device in TryDeviceIter::with_builder(&self.dir, self.builder.clone())? {
+            let device = device?;
+            devices.insert(device.name().to_string(), Arc::new(device));
+        }
+        if let Some(ref led_dir) = self.led_dir {
+            for led in LedIter::new(led_dir)? {
+                let led = led?;
+                if led.is_keyboard() {
+                    devices.insert(led.name().to_string(), Arc::new(led));
+                }
+            }
+        }
+        *self.devices.lock().unwrap() = devices;
+        Ok(())
+    }
+
+    /// Looks up a device among those found by the last scan.
+    fn device(&self, name: &str) -> std::result::Result<Arc<dyn Backlight>, String> {
+        self.devices
+            .lock()
+            .unwrap()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("no device named '{}'", name))
+    }
+
+    /// Checks that the client may write `target` beyond the limits of `device`:
+    /// root and the admin group always may, the others only toward the limits
+    /// from a brightness already out of them.
+    fn check_force(&self, device: &dyn Backlight, target: i32) -> std::result::Result<(), String> {
+        if self.may_force {
+            return Ok(());
+        }
+        let e = |e: Error| e.to_string();
+        let current = device.brightness().map_err(e)?;
+        let min = device.min_brightness().map_err(e)?;
+        let max = device.ceiling_brightness().map_err(e)?;
+        if (current.min(min)..=current.max(max)).contains(&target) {
+            Ok(())
+        } else {
+            Err("only root and the admin group may force the brightness".into())
+        }
+    }
+
+    fn handle(&self, line: &str) -> String {
+        match self.execute(line) {
+            Ok(reply) if reply.is_empty() => "ok".to_string(),
+            Ok(reply) => format!("ok {}", reply),
+            Err(message) => format!("error {}", message.replace('\n', " ")),
+        }
+    }
+
+    fn execute(&self, line: &str) -> std::result::Result<String, String> {
+        fn parse<T: std::str::FromStr>(
+            word: Option<&&str>,
+            what: &str,
+        ) -> std::result::Result<T, String> {
+            let word = word.ok_or_else(|| format!("missing {}", what))?;
+            word.parse()
+                .map_err(|_| format!("invalid {} '{}'", what, word))
+        }
+
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let (command, args) = match words.split_first() {
+            Some((command, args)) => (*command, args),
+            None => return Err("empty request".to_string()),
+        };
+        if command == "list" {
+            self.rescan().map_err(|e| e.to_string())?;
+            let devices = self.devices.lock().unwrap();
+            return Ok(devices.keys().cloned().collect::<Vec<_>>().join(" "));
+        }
+
+        if !COMMANDS.contains(&command) {
+            return Err(format!("unknown command '{}'", command));
+        }
+        let name: String = parse(args.first(), "device name")?;
+        let device = self.device(&name)?;
+        let e = |e: Error| e.to_string();
+        let reply = match command {
+            "get" => format!(
+                "{} {} {}",
+                device.brightness().map_err(e)?,
+                device.max_brightness().map_err(e)?,
+                device.power_state().map_err(e)?
+            ),
+            "on" => device.power_on().map(|_| String::new()).map_err(e)?,
+            "off" => device.power_off().map(|_| String::new()).map_err(e)?,
+            "toggle" => device.toggle().map_err(e)?.to_string(),
+            "set" => {
+                let state: PowerState = parse(args.get(1), "power state")?;
+                device.set_power_state(state).map_err(e)?;
+                String::new()
+            }
+            "brightness" => {
+                device
+                    .set_brightness(parse(args.get(1), "brightness")?)
+                    .map_err(e)?;
+                String::new()
+            }
+            "force" => {
+                let target: i32 = parse(args.get(1), "brightness")?;
+                self.check_force(&*device, target)?;
+                device.force_brightness(target).map_err(e)?;
+                String::new()
+            }
+            "step" => {
+                let step: Step = parse(args.get(1), "step")?;
+                device.step(step).map_err(e)?.to_string()
+            }
+            "fade" => {
+                let target: i32 = parse(args.get(1), "brightness")?;
+                let ms: u64 = parse(args.get(2), "duration")?;
+                let curve: Curve = parse(args.get(3), "curve")?;
+                let force = match args.get(4) {
+                    None => false,
+                    Some(&"force") => true,
+                    Some(word) => return Err(format!("invalid option '{}'", word)),
+                };
+                let duration = Duration::from_millis(ms);
+                if duration > MAX_FADE {
+                    return Err(format!(
+                        "invalid duration '{}': expected at most {}",
+                        ms,
+                        MAX_FADE.as_millis()
+                    ));
+                }
+                if force {
+                    self.check_force(&*device, target)?;
+                    device.force_fade_to_until(target, duration, curve, &self.stopping)
+                } else {
+                    device.fade_to_until(target, duration, curve, &self.stopping)
+                }
+                .map_err(e)?
+                .to_string()
+            }
+            _ => unreachable!(),
+        };
+        Ok(reply)
+    }
+}
+
+/// The commands applying to a device.
+const COMMANDS: [&str; 9] = [
+    "get",
+    "on",
+    "off",
+    "toggle",
+    "set",
+    "brightness",
+    "force",
+    "step",
+    "fade",
+];
+
+/// The client side of the protocol.
+///
+/// See [`Server`] for an example.
+///
+/// [`Server`]: struct.Server.html
+pub struct Client {
+    socket: PathBuf,
+    reader: BufReader<UnixStream>,
+    writer: UnixStream,
+}
+
+impl Client {
+    /// Connects to the daemon listening at `socket`.
+    pub fn connect<P: AsRef<Path>>(socket: P) -> Result<Self> {
+        let socket = socket.as_ref();
+        let writer = UnixStream::connect(socket).map_err(|e| Error::io(socket, e))?;
+        let reader = writer.try_clone().map_err(|e| Error::io(socket, e))?;
+        Ok(Self {
+            socket: socket.to_path_buf(),
+            reader: BufReader::new(reader),
+            writer,
+        })
+    }
+
+    /// Sends a raw request and returns the payload of the response,
+    /// or [`Error::Daemon`] if the daemon reported an error.
+    ///
+    /// [`Error::Daemon`]: ../enum.Error.html#variant.Daemon
+    pub fn request(&mut self, request: &str) -> Result<String> {
+        let socket = self.socket.clone();
+        let io_error = |e| Error::io(&socket, e);
+        writeln!(self.writer, "{}", request).map_err(io_error)?;
+        let mut line = String::new();
+        if self.reader.read_line(&mut line).map_err(io_error)? == 0 {
+            let e = io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "the daemon closed the connection",
+            );
+            return Err(io_error(e));
+        }
+        let line = line.trim_end();
+        match line.split_once(' ').unwrap_or((line, "")) {
+            ("ok", payload) => Ok(payload.to_string()),
+            ("error", message) => Err(Error::Daemon {
+                path: self.socket.clone(),
+                message: message.to_string(),
+            }),
+            _ => Err(Error::parse(&self.socket, line, "a response of the daemon")),
+        }
+    }
+
+    fn parse<T: std::str::FromStr>(&self, payload: &str) -> Result<T> {
+        payload
+            .parse()
+            .map_err(|_| Error::parse(&self.socket, payload, "a response of the daemon"))
+    }
+
+    /// Lists the names of the devices served by the daemon.
+    pub fn list(&mut self) -> Result<Vec<String>> {
+        let payload = self.request("list")?;
+        Ok(payload.split_whitespace().map(String::from).collect())
+    }
+
+    /// Reads the brightness, maximum brightness and power state of a device.
+    pub fn get(&mut self, name: &str) -> Result<(i32, i32, PowerState)> {
+        let payload = self.request(&format!("get {}", name))?;
+        let words: Vec<&str> = payload.split_whitespace().collect();
+        if words.len() != 3 {
+            return Err(Error::parse(
+                &self.socket,
+                &payload,
+                "a response of the daemon",
+            ));
+        }
+        Ok((
+            self.parse(words[0])?,
+            self.parse(words[1])?,
+            self.parse(words[2])?,
+        ))
+    }
+
+    /// Turns a device ON.
+    pub fn power_on(&mut self, name: &str) -> Result<()> {
+        self.request(&format!("on {}", name)).map(|_| ())
+    }
+
+    /// Turns a device OFF.
+    pub fn power_off(&mut self, name: &str) -> Result<()> {
+        self.request(&format!("off {}", name)).map(|_| ())
+    }
+
+    /// Toggles a device, returning its new state.
+    pub fn toggle(&mut self, name: &str) -> Result<PowerState> {
+        let payload = self.request(&format!("toggle {}", name))?;
+        self.parse(&payload)
+    }
+
+    /// Sets the power state of a device.
+    pub fn set_power_state(&mut self, name: &str, state: PowerState) -> Result<()> {
+        self.request(&format!("set {} {}", name, state)).map(|_| ())
+    }
+
+    /// Sets the raw brightness of a device, respecting the floor of the daemon.
+    pub fn set_brightness(&mut self, name: &str, value: i32) -> Result<()> {
+        self.request(&format!("brightness {} {}", name, value))
+            .map(|_| ())
+    }
+
+    /// Sets the raw brightness of a device, ignoring the floor of the daemon.
+    pub fn force_brightness(&mut self, name: &str, value: i32) -> Result<()> {
+        self.request(&format!("force {} {}", name, value))
+            .map(|_| ())
+    }
+
+    /// Changes the brightness of a device by `step`, returning the new raw value.
+    pub fn step(&mut self, name: &str, step: Step) -> Result<i32> {
+        let payload = self.request(&format!("step {} {}", name, step))?;
+        self.parse(&payload)
+    }
+
+    /// Fades the brightness of a device to the raw `target`, returning the value reached.
+    pub fn fade_to(
+        &mut self,
+        name: &str,
+        target: i32,
+        duration: Duration,
+        curve: Curve,
+    ) -> Result<i32> {
+        let payload = self.request(&format!(
+            "fade {} {} {} {}",
+            name,
+            target,
+            duration.as_millis(),
+            curve
+        ))?;
+        self.parse(&payload)
+    }
+
+    /// Like [`fade_to`], but ignoring the floor of the daemon.
+    ///
+    /// [`fade_to`]: #method.fade_to
+    pub fn force_fade_to(
+        &mut self,
+        name: &str,
+        target: i32,
+        duration: Duration,
+        curve: Curve,
+    ) -> Result<i32> {
+        let payload = self.request(&format!(
+            "fade {} {} {} {} force",
+            name,
+            target,
+            duration.as_millis(),
+            curve
+        ))?;
+        self.parse(&payload)
+    }
+}
@@ -0,0 +1,878 @@
+//! External monitors controlled over DDC/CI.
+//!
+//! DDC/CI runs over the i2c bus of the video cable, which the `i2c-dev`
+//! driver exposes as `/dev/i2c-*`. The monitor answers at [`DDC_ADDRESS`]
+//! and exposes its settings as VCP codes, among which [`VCP_LUMINANCE`] and
+//! [`VCP_POWER_MODE`]; its EDID, at [`EDID_ADDRESS`], identifies it.
+//!
+//! Monitors are slow and sometimes drop requests, so a [`DdcMonitor`] waits
+//! between requests and retries the failed ones. The bus is abstracted by
+//! [`I2cBus`], so that the tests can stand a simulated monitor in for a real
+//! one.
+//!
+//! [`DDC_ADDRESS`]: constant.DDC_ADDRESS.html
+//! [`VCP_LUMINANCE`]: constant.VCP_LUMINANCE.html
+//! [`VCP_POWER_MODE`]: constant.VCP_POWER_MODE.html
+//! [`EDID_ADDRESS`]: constant.EDID_ADDRESS.html
+//! [`DdcMonitor`]: struct.DdcMonitor.html
+//! [`I2cBus`]: trait.I2cBus.html
+
+use crate::{raw_limits, sys, Backlight, Error, Level, PowerState, Result, Scale, BRIGHTNESS};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The default directory where to look for i2c-dev nodes.
+pub const I2C_DEV_PATH: &str = "/dev";
+
+/// The default directory where the DRM connectors are listed.
+pub const DRM_PATH: &str = "/sys/class/drm";
+
+/// The i2c address at which monitors answer DDC/CI requests.
+pub const DDC_ADDRESS: u16 = 0x37;
+
+/// The i2c address at which monitors expose their EDID.
+pub const EDID_ADDRESS: u16 = 0x50;
+
+/// The VCP code of the luminance, i.e. the brightness of the backlight.
+pub const VCP_LUMINANCE: u8 = 0x10;
+
+/// The VCP code of the power mode: 1 is ON, 2 standby, 3 suspend,
+/// 4 OFF and 5 OFF through the power button.
+///
+/// Mode 5 is never written, since some monitors then only wake up
+/// through their power button.
+pub const VCP_POWER_MODE: u8 = 0xD6;
+
+/// The default time given to a monitor to process a request,
+/// and left between two requests.
+pub const DDC_DELAY: Duration = Duration::from_millis(50);
+
+/// The default number of times a failed request is sent again.
+pub const DDC_RETRIES: u32 = 3;
+
+/// The first byte of the messages of the host.
+const HOST_ADDRESS: u8 = 0x51;
+/// The initial value of the checksum of the messages of the host,
+/// i.e. the write address of the monitor.
+const REQUEST_CHECKSUM: u8 = 0x6E;
+/// The initial value of the checksum of the replies of the monitor.
+const REPLY_CHECKSUM: u8 = 0x50;
+const GET_VCP: u8 = 0x01;
+const GET_VCP_REPLY: u8 = 0x02;
+const SET_VCP: u8 = 0x03;
+/// The length of a reply to [`GET_VCP`], including the checksum.
+const GET_VCP_REPLY_LEN: usize = 11;
+const EDID_HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+const EDID_LEN: usize = 128;
+
+/// An i2c bus on which a monitor can be reached.
+pub trait I2cBus: Send {
+    /// Writes `data` to the peer at `address`.
+    fn write(&mut self, address: u16, data: &[u8]) -> io::Result<()>;
+
+    /// Reads exactly `buf.len()` bytes from the peer at `address`.
+    fn read(&mut self, address: u16, buf: &mut [u8]) -> io::Result<()>;
+}
+
+/// An i2c bus exposed by the `i2c-dev` driver, like `/dev/i2c-4`.
+#[derive(Debug)]
+pub struct I2cDevice {
+    file: File,
+    address: Option<u16>,
+}
+
+impl I2cDevice {
+    /// Opens the i2c-dev node at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self {
+            file,
+            address: None,
+        })
+    }
+
+    fn set_address(&mut self, address: u16) -> io::Result<()> {
+        if self.address != Some(address) {
+            sys::i2c_set_address(&self.file, address)?;
+            self.address = Some(address);
+        }
+        Ok(())
+    }
+}
+
+impl I2cBus for I2cDevice {
+    fn write(&mut self, address: u16, data: &[u8]) -> io::Result<()> {
+        self.set_address(address)?;
+        self.file.write_all(data)
+    }
+
+    fn read(&mut self, address: u16, buf: &mut [u8]) -> io::Result<()> {
+        self.set_address(address)?;
+        self.file.read_exact(buf)
+    }
+}
+
+/// The identification of a monitor, read from its EDID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Edid {
+    manufacturer: String,
+    product: u16,
+    serial: u32,
+    name: Option<String>,
+    serial_number: Option<String>,
+}
+
+impl Edid {
+    /// Parses the 128-byte base block of an EDID.
+    ///
+    /// Returns `None` if the header or the checksum is wrong.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let bytes = bytes.get(..EDID_LEN)?;
+        if bytes[..8] != EDID_HEADER || bytes.iter().fold(0u8, |a, b| a.wrapping_add(*b)) != 0 {
+            return None;
+        }
+        // Three letters of five bits each, 'A' being 1.
+        let id = u16::from_be_bytes([bytes[8], bytes[9]]);
+        let manufacturer = [10, 5, 0]
+            .iter()
+            .map(|shift| char::from(b'@' + ((id >> shift) & 0x1F) as u8))
+            .collect();
+        let mut edid = Edid {
+            manufacturer,
+            product: u16::from_le_bytes([bytes[10], bytes[11]]),
+            serial: u32::from_le_bytes([bytes[12], bytes[13], bytes[14], bytes[15]]),
+            name: None,
+            serial_number: None,
+        };
+        // Display descriptors have a null pixel clock and their tag at byte 3.
+        for descriptor in bytes[54..126].chunks(18) {
+            if descriptor[..3] != [0, 0, 0] {
+                continue;
+            }
+            let text = String::from_utf8_lossy(&descriptor[5..])
+                .split('\n')
+                .next()
+                .unwrap_or("")
+                .trim()
+                .to_string();
+            match descriptor[3] {
+                0xFC => edid.name = Some(text),
+                0xFF => edid.serial_number = Some(text),
+                _ => {}
+            }
+        }
+        Some(edid)
+    }
+
+    /// Returns the three-letter PNP identifier of the manufacturer, like `DEL`.
+    pub fn manufacturer(&self) -> &str {
+        &self.manufacturer
+    }
+
+    /// Returns the product code.
+    pub fn product(&self) -> u16 {
+        self.product
+    }
+
+    /// Returns the numeric serial number, often 0.
+    pub fn serial(&self) -> u32 {
+        self.serial
+    }
+
+    /// Returns the name of the model, like `DELL U2720Q`.
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the serial number as a string, when the monitor has one.
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+
+    /// Returns an identifier of the monitor, like `DEL-A0B1-12345`,
+    /// made of the manufacturer, the product code and the serial number.
+    ///
+    /// It does not depend on the connector the monitor is plugged in.
+    pub fn id(&self) -> String {
+        match self.serial_number {
+            Some(ref serial) if !serial.is_empty() => {
+                format!("{}-{:04X}-{}", self.manufacturer, self.product, serial)
+            }
+            _ => format!("{}-{:04X}-{}", self.manufacturer, self.product, self.serial),
+        }
+    }
+
+    /// Encodes a base block with the given identification.
+    #[cfg(test)]
+    fn encode(manufacturer: &str, product: u16, serial: u32, name: &str) -> Vec<u8> {
+        let mut bytes = vec![0; EDID_LEN];
+        bytes[..8].copy_from_slice(&EDID_HEADER);
+        let id = manufacturer.bytes().take(3).fold(0u16, |id, c| {
+            (id << 5) | u16::from(c.wrapping_sub(b'@') & 0x1F)
+        });
+        bytes[8..10].copy_from_slice(&id.to_be_bytes());
+        bytes[10..12].copy_from_slice(&product.to_le_bytes());
+        bytes[12..16].copy_from_slice(&serial.to_le_bytes());
+        // EDID 1.4.
+        bytes[18] = 1;
+        bytes[19] = 4;
+        let descriptor = &mut bytes[54..72];
+        descriptor[3] = 0xFC;
+        let mut text = name.bytes().take(13).collect::<Vec<_>>();
+        if text.len() < 13 {
+            text.push(b'\n');
+        }
+        text.resize(13, b' ');
+        descriptor[5..].copy_from_slice(&text);
+        let sum = bytes.iter().fold(0u8, |a, b| a.wrapping_add(*b));
+        bytes[EDID_LEN - 1] = sum.wrapping_neg();
+        bytes
+    }
+}
+
+impl fmt::Display for Edid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.name {
+            Some(ref name) => write!(f, "{} ({})", name, self.id()),
+            None => f.write_str(&self.id()),
+        }
+    }
+}
+
+/// The bus of a monitor, with the instant of the last request.
+struct Bus {
+    inner: Box<dyn I2cBus>,
+    last: Option<Instant>,
+    /// The maximum luminance, which does not change.
+    max_luminance: Option<u16>,
+}
+
+/// An external monitor controlled over DDC/CI.
+///
+/// Every request waits for [`delay`] since the previous one, and the failed
+/// requests, or those with a corrupted reply, are sent up to [`retries`]
+/// more times. Like a [`Device`], a monitor has a floor, a ceiling and a
+/// scale. See the [module documentation](index.html) for an example.
+///
+/// [`delay`]: #method.delay
+/// [`retries`]: #method.retries
+/// [`Device`]: ../struct.Device.html
+pub struct DdcMonitor {
+    name: String,
+    path: Option<PathBuf>,
+    edid: Option<Edid>,
+    retries: u32,
+    delay: Duration,
+    scale: Scale,
+    floor: Level,
+    ceiling: Option<Level>,
+    bus: Mutex<Bus>,
+}
+
+impl DdcMonitor {
+    /// Opens the monitor on the i2c-dev node at `path`, named after the node.
+    ///
+    /// The EDID is read on the way, and [`Error::NotFound`] is returned
+    /// if there is none, since there is no monitor on the bus then.
+    ///
+    /// [`Error::NotFound`]: ../enum.Error.html#variant.NotFound
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let bus = I2cDevice::open(path).map_err(|e| Error::io(path, e))?;
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("")
+            .to_string();
+        let mut monitor = Self::with_bus(name, bus);
+        if monitor.edid.is_none() {
+            return Err(Error::NotFound {
+                path: path.to_path_buf(),
+            });
+        }
+        monitor.path = Some(path.to_path_buf());
+        Ok(monitor)
+    }
+
+    /// Creates a monitor named `name` on `bus`, reading its EDID if possible.
+    pub fn with_bus<S, B>(name: S, bus: B) -> Self
+    where
+        S: Into<String>,
+        B: I2cBus + 'static,
+    {
+        let mut bus = Bus {
+            inner: Box::new(bus),
+            last: None,
+            max_luminance: None,
+        };
+        let edid = read_edid(&mut *bus.inner)
+            .ok()
+            .and_then(|bytes| Edid::parse(&bytes));
+        Self {
+            name: name.into(),
+            path: None,
+            edid,
+            retries: DDC_RETRIES,
+            delay: DDC_DELAY,
+            scale: Scale::Linear,
+            floor: Level::Raw(0),
+            ceiling: None,
+            bus: Mutex::new(bus),
+        }
+    }
+
+    /// Sets the number of times a failed request is sent again,
+    /// [`DDC_RETRIES`] by default.
+    ///
+    /// [`DDC_RETRIES`]: constant.DDC_RETRIES.html
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets the time given to the monitor to process a request,
+    /// [`DDC_DELAY`] by default.
+    ///
+    /// [`DDC_DELAY`]: constant.DDC_DELAY.html
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Returns the name of the monitor, like `i2c-4`.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the path of the i2c-dev node of the monitor, if it was opened from one.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns the EDID of the monitor, if it could be read.
+    pub fn edid(&self) -> Option<&Edid> {
+        self.edid.as_ref()
+    }
+
+    /// Returns the scale used to convert percentages to raw values.
+    pub fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    /// Sets the scale used to convert percentages to raw values.
+    pub fn set_scale(&mut self, scale: Scale) {
+        self.scale = scale;
+    }
+
+    /// Returns the minimum luminance that set and step operations respect.
+    pub fn floor(&self) -> Level {
+        self.floor
+    }
+
+    /// Sets the minimum luminance that set and step operations respect,
+    /// `Level::Raw(0)` by default.
+    pub fn set_floor(&mut self, floor: Level) {
+        self.floor = floor;
+    }
+
+    /// Returns the maximum luminance that set and step operations respect,
+    /// if any.
+    pub fn ceiling(&self) -> Option<Level> {
+        self.ceiling
+    }
+
+    /// Sets the maximum luminance that set and step operations respect.
+    ///
+    /// Setting it to `None` disables the ceiling.
+    pub fn set_ceiling(&mut self, ceiling: Option<Level>) {
+        self.ceiling = ceiling;
+    }
+
+    /// Reads the current and maximum values of the VCP `code`.
+    pub fn get_vcp(&self, code: u8) -> Result<(u16, u16)> {
+        let (current, max) = self.request(|bus, delay, path| {
+            bus.write(DDC_ADDRESS, &message(&[GET_VCP, code]))
+                .map_err(|e| Error::io(path, e))?;
+            thread::sleep(delay);
+            let mut reply = [0; GET_VCP_REPLY_LEN];
+            bus.read(DDC_ADDRESS, &mut reply)
+                .map_err(|e| Error::io(path, e))?;
+            parse_get_vcp_reply(&reply, code, path)
+        })?;
+        if code == VCP_LUMINANCE {
+            self.bus.lock().unwrap().max_luminance = Some(max);
+        }
+        Ok((current, max))
+    }
+
+    /// Sets the VCP `code` to `value`.
+    pub fn set_vcp(&self, code: u8, value: u16) -> Result<()> {
+        let [high, low] = value.to_be_bytes();
+        self.request(|bus, _, path| {
+            bus.write(DDC_ADDRESS, &message(&[SET_VCP, code, high, low]))
+                .map_err(|e| Error::io(path, e))
+        })
+    }
+
+    /// Sets the luminance to `value`, which must lie in `min..=max`.
+    fn set_luminance(&self, value: i32, min: i32, max: i32) -> Result<()> {
+        if value < min || value > max {
+            return Err(Error::invalid_value(
+                self.error_path().join(BRIGHTNESS),
+                value,
+                format!("{}..={}", min, max),
+            ));
+        }
+        self.set_vcp(VCP_LUMINANCE, value as u16)
+    }
+
+    /// Runs `f` on the bus, after the delay since the previous request,
+    /// until it succeeds or the retries are exhausted.
+    ///
+    /// Only I/O errors and corrupted replies are retried.
+    fn request<T, F>(&self, mut f: F) -> Result<T>
+    where
+        F: FnMut(&mut dyn I2cBus, Duration, &Path) -> Result<T>,
+    {
+        let path = self.error_path();
+        let mut bus = self.bus.lock().unwrap();
+        let mut attempt = 0;
+        loop {
+            if let Some(last) = bus.last {
+                let elapsed = last.elapsed();
+                if elapsed < self.delay {
+                    thread::sleep(self.delay - elapsed);
+                }
+            }
+            let result = f(&mut *bus.inner, self.delay, &path);
+            bus.last = Some(Instant::now());
+            match result {
+                Err(Error::Io { .. }) | Err(Error::Parse { .. }) if attempt < self.retries => {
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns the path used in errors, made up from the name if there is no node.
+    fn error_path(&self) -> PathBuf {
+        self.path
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(&self.name))
+    }
+}
+
+impl fmt::Debug for DdcMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DdcMonitor")
+            .field("name", &self.name)
+            .field("path", &self.path)
+            .field("edid", &self.edid)
+            .field("retries", &self.retries)
+            .field("delay", &self.delay)
+            .field("scale", &self.scale)
+            .field("floor", &self.floor)
+            .field("ceiling", &self.ceiling)
+            .finish()
+    }
+}
+
+impl Backlight for DdcMonitor {
+    fn name(&self) -> &str {
+        DdcMonitor::name(self)
+    }
+
+    fn path(&self) -> Option<&Path> {
+        DdcMonitor::path(self)
+    }
+
+    fn id(&self) -> Result<String> {
+        Ok(match self.edid {
+            Some(ref edid) => edid.id(),
+            None => self.name.clone(),
+        })
+    }
+
+    fn power_state(&self) -> Result<PowerState> {
+        let (mode, _) = self.get_vcp(VCP_POWER_MODE)?;
+        match mode {
+            1 => Ok(PowerState::Unblank),
+            2 => Ok(PowerState::HSyncSuspend),
+            3 => Ok(PowerState::VSyncSuspend),
+            4 | 5 => Ok(PowerState::Powerdown),
+            _ => Err(Error::invalid_value(self.error_path(), mode, "1..=5")),
+        }
+    }
+
+    /// Sets the power mode of the monitor.
+    ///
+    /// A monitor cannot blank while staying powered, so
+    /// [`PowerState::Normal`] turns it off like [`PowerState::Powerdown`],
+    /// and reads back as the latter.
+    ///
+    /// [`PowerState::Normal`]: ../enum.PowerState.html#variant.Normal
+    /// [`PowerState::Powerdown`]: ../enum.PowerState.html#variant.Powerdown
+    fn set_power_state(&self, state: PowerState) -> Result<()> {
+        let mode = match state {
+            PowerState::Unblank => 1,
+            PowerState::HSyncSuspend => 2,
+            PowerState::VSyncSuspend => 3,
+            PowerState::Normal | PowerState::Powerdown => 4,
+        };
+        self.set_vcp(VCP_POWER_MODE, mode)
+    }
+
+    fn brightness(&self) -> Result<i32> {
+        Ok(i32::from(self.get_vcp(VCP_LUMINANCE)?.0))
+    }
+
+    fn max_brightness(&self) -> Result<i32> {
+        let cached = self.bus.lock().unwrap().max_luminance;
+        match cached {
+            Some(max) => Ok(i32::from(max)),
+            None => Ok(i32::from(self.get_vcp(VCP_LUMINANCE)?.1)),
+        }
+    }
+
+    fn min_brightness(&self) -> Result<i32> {
+        Ok(raw_limits(self, self.floor, None)?.0)
+    }
+
+    fn ceiling_brightness(&self) -> Result<i32> {
+        Ok(raw_limits(self, self.floor, self.ceiling)?.1)
+    }
+
+    fn set_brightness(&self, value: i32) -> Result<()> {
+        self.set_luminance(value, self.min_brightness()?, self.ceiling_brightness()?)
+    }
+
+    fn force_brightness(&self, value: i32) -> Result<()> {
+        self.set_luminance(value, 0, self.max_brightness()?)
+    }
+
+    fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    fn set_floor(&mut self, floor: Level) -> Result<()> {
+        self.floor = floor;
+        Ok(())
+    }
+
+    fn set_ceiling(&mut self, ceiling: Option<Level>) -> Result<()> {
+        self.ceiling = ceiling;
+        Ok(())
+    }
+
+    fn set_scale(&mut self, scale: Scale) -> Result<()> {
+        self.scale = scale;
+        Ok(())
+    }
+}
+
+/// Returns the names of the i2c adapters, like `i2c-4`, that carry the DDC
+/// of a connector listed in `drm`, usually [`DRM_PATH`].
+///
+/// An adapter belongs to a connector when the connector links to it as its
+/// `ddc`, or when it is a child of the connector, as DisplayPort AUX
+/// channels are. The names are sorted.
+///
+/// [`DRM_PATH`]: constant.DRM_PATH.html
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::ddc::ddc_adapters;
+/// # use std::fs;
+/// let drm = std::env::temp_dir().join("rust-lcd-doc-ddc-adapters");
+/// # let _ = fs::remove_dir_all(&drm);
+/// fs::create_dir_all(drm.join("card0-DP-1").join("i2c-7")).unwrap();
+/// fs::create_dir_all(drm.join("card0-HDMI-A-1")).unwrap();
+/// std::os::unix::fs::symlink("../i2c-3", drm.join("card0-HDMI-A-1").join("ddc")).unwrap();
+/// fs::create_dir_all(drm.join("card0")).unwrap();
+/// assert_eq!(ddc_adapters(&drm).unwrap(), ["i2c-3", "i2c-7"]);
+/// # fs::remove_dir_all(&drm).unwrap();
+/// ```
+pub fn ddc_adapters<P: AsRef<Path>>(drm: P) -> Result<Vec<String>> {
+    let drm = drm.as_ref();
+    let mut adapters = Vec::new();
+    for entry in fs::read_dir(drm).map_err(|e| Error::io(drm, e))? {
+        let entry = entry.map_err(|e| Error::io(drm, e))?;
+        // The connectors are named after their card, like `card0-DP-1`.
+        if !entry.file_name().to_string_lossy().contains('-') {
+            continue;
+        }
+        let connector = entry.path();
+        if let Ok(ddc) = fs::read_link(connector.join("ddc")) {
+            if let Some(name) = ddc.file_name() {
+                adapters.push(name.to_string_lossy().into_owned());
+            }
+        }
+        if let Ok(children) = fs::read_dir(&connector) {
+            for child in children.flatten() {
+                let name = child.file_name().to_string_lossy().into_owned();
+                if name.starts_with("i2c-") {
+                    adapters.push(name);
+                }
+            }
+        }
+    }
+    adapters.sort();
+    adapters.dedup();
+    Ok(adapters)
+}
+
+/// An iterator over the monitors reachable through the i2c-dev nodes of a
+/// folder, [`I2C_DEV_PATH`] by default.
+///
+/// Only the nodes of the DDC adapters of the DRM connectors, as listed by
+/// [`ddc_adapters`], are opened, so that no other i2c device, like a memory
+/// module or a sensor, is ever written to. The nodes that cannot be opened,
+/// and the buses without a monitor answering with an EDID, are skipped.
+///
+/// [`I2C_DEV_PATH`]: constant.I2C_DEV_PATH.html
+/// [`ddc_adapters`]: fn.ddc_adapters.html
+pub struct DdcIter {
+    paths: std::vec::IntoIter<PathBuf>,
+}
+
+impl DdcIter {
+    /// Creates an iterator over the monitors on the i2c-dev nodes of `path`,
+    /// with the connectors of [`DRM_PATH`], or returns the error reading them.
+    ///
+    /// [`DRM_PATH`]: constant.DRM_PATH.html
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_drm(path, DRM_PATH)
+    }
+
+    /// Creates an iterator over the monitors on the i2c-dev nodes of `path`,
+    /// with the connectors listed in `drm`, or returns the error reading them.
+    pub fn with_drm<P: AsRef<Path>, Q: AsRef<Path>>(path: P, drm: Q) -> Result<Self> {
+        let dir = path.as_ref();
+        let adapters = ddc_adapters(drm)?;
+        let paths: Vec<PathBuf> = adapters.iter().map(|name| dir.join(name)).collect();
+        Ok(Self {
+            paths: paths.into_iter(),
+        })
+    }
+}
+
+impl Iterator for DdcIter {
+    type Item = DdcMonitor;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.paths.find_map(|path| DdcMonitor::open(path).ok())
+    }
+}
+
+/// XORs `bytes` into `init`, as the checksums of DDC/CI do.
+fn checksum(init: u8, bytes: &[u8]) -> u8 {
+    bytes.iter().fold(init, |sum, byte| sum ^ byte)
+}
+
+/// Frames a request of the host.
+fn message(payload: &[u8]) -> Vec<u8> {
+    let mut message = vec![HOST_ADDRESS, 0x80 | payload.len() as u8];
+    message.extend_from_slice(payload);
+    message.push(checksum(REQUEST_CHECKSUM, &message));
+    message
+}
+
+fn parse_get_vcp_reply(reply: &[u8], code: u8, path: &Path) -> Result<(u16, u16)> {
+    let last = reply.len() - 1;
+    let valid = reply[1] & 0x7F == (last - 2) as u8
+        && reply[2] == GET_VCP_REPLY
+        && reply[4] == code
+        && checksum(REPLY_CHECKSUM, &reply[..last]) == reply[last];
+    if !valid {
+        let content: Vec<String> = reply.iter().map(|b| format!("{:02x}", b)).collect();
+        return Err(Error::parse(path, &content.join(" "), "a DDC/CI reply"));
+    }
+    if reply[3] != 0 {
+        return Err(Error::invalid_value(
+            path,
+            format!("VCP 0x{:02X}", code),
+            "a VCP code supported by the monitor",
+        ));
+    }
+    Ok((
+        u16::from_be_bytes([reply[8], reply[9]]),
+        u16::from_be_bytes([reply[6], reply[7]]),
+    ))
+}
+
+fn read_edid(bus: &mut dyn I2cBus) -> io::Result<Vec<u8>> {
+    bus.write(EDID_ADDRESS, &[0])?;
+    let mut edid = vec![0; EDID_LEN];
+    bus.read(EDID_ADDRESS, &mut edid)?;
+    Ok(edid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::sync::Arc;
+
+    /// A simulated monitor, answering DDC/CI requests and EDID reads in memory.
+    ///
+    /// It starts ON, with a luminance of 50 out of 100. Clones share the same
+    /// state, so a test can keep one to inspect the values set through another,
+    /// and inject failures.
+    #[derive(Debug, Clone)]
+    struct SimulatedMonitor {
+        state: Arc<Mutex<Simulation>>,
+    }
+
+    #[derive(Debug)]
+    struct Simulation {
+        edid: Vec<u8>,
+        edid_offset: usize,
+        /// The current and maximum values of the supported VCP codes.
+        vcp: BTreeMap<u8, (u16, u16)>,
+        reply: Vec<u8>,
+        failures: u32,
+    }
+
+    impl SimulatedMonitor {
+        /// Creates a monitor with the given EDID identification.
+        fn new(manufacturer: &str, product: u16, serial: u32, name: &str) -> Self {
+            let mut vcp = BTreeMap::new();
+            vcp.insert(VCP_LUMINANCE, (50, 100));
+            vcp.insert(VCP_POWER_MODE, (1, 5));
+            Self {
+                state: Arc::new(Mutex::new(Simulation {
+                    edid: Edid::encode(manufacturer, product, serial, name),
+                    edid_offset: 0,
+                    vcp,
+                    reply: Vec::new(),
+                    failures: 0,
+                })),
+            }
+        }
+
+        /// Makes the next `count` transfers fail with an I/O error.
+        fn fail_next(&self, count: u32) {
+            self.state.lock().unwrap().failures = count;
+        }
+
+        /// Returns the current value of the VCP `code`, if it is supported.
+        fn vcp(&self, code: u8) -> Option<u16> {
+            self.state
+                .lock()
+                .unwrap()
+                .vcp
+                .get(&code)
+                .map(|&(current, _)| current)
+        }
+    }
+
+    impl Simulation {
+        fn fail(&mut self) -> io::Result<()> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return Err(io::Error::new(io::ErrorKind::Other, "simulated failure"));
+            }
+            Ok(())
+        }
+
+        fn handle(&mut self, data: &[u8]) {
+            self.reply.clear();
+            let valid = data.len() >= 3
+                && data[0] == HOST_ADDRESS
+                && usize::from(data[1] & 0x7F) == data.len() - 3
+                && checksum(REQUEST_CHECKSUM, &data[..data.len() - 1]) == data[data.len() - 1];
+            if !valid {
+                return;
+            }
+            match data[2..data.len() - 1] {
+                [GET_VCP, code] => {
+                    let (result, (current, max)) = match self.vcp.get(&code) {
+                        Some(&values) => (0, values),
+                        None => (1, (0, 0)),
+                    };
+                    let [max_high, max_low] = max.to_be_bytes();
+                    let [high, low] = current.to_be_bytes();
+                    let mut reply = vec![
+                        REQUEST_CHECKSUM,
+                        0x88,
+                        GET_VCP_REPLY,
+                        result,
+                        code,
+                        0,
+                        max_high,
+                        max_low,
+                        high,
+                        low,
+                    ];
+                    reply.push(checksum(REPLY_CHECKSUM, &reply));
+                    self.reply = reply;
+                }
+                [SET_VCP, code, high, low] => {
+                    if let Some(values) = self.vcp.get_mut(&code) {
+                        values.0 = u16::from_be_bytes([high, low]).min(values.1);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    impl I2cBus for SimulatedMonitor {
+        fn write(&mut self, address: u16, data: &[u8]) -> io::Result<()> {
+            let mut state = self.state.lock().unwrap();
+            state.fail()?;
+            match address {
+                EDID_ADDRESS => {
+                    state.edid_offset = usize::from(data.first().copied().unwrap_or(0));
+                    Ok(())
+                }
+                DDC_ADDRESS => {
+                    state.handle(data);
+                    Ok(())
+                }
+                _ => Err(io::Error::from(io::ErrorKind::NotFound)),
+            }
+        }
+
+        fn read(&mut self, address: u16, buf: &mut [u8]) -> io::Result<()> {
+            let mut state = self.state.lock().unwrap();
+            state.fail()?;
+            let source = match address {
+                EDID_ADDRESS => &state.edid[state.edid_offset.min(EDID_LEN)..],
+                DDC_ADDRESS => &state.reply[..],
+                _ => return Err(io::Error::from(io::ErrorKind::NotFound)),
+            };
+            // Like a real bus, reading past the data yields garbage, here zeros.
+            for (i, byte) in buf.iter_mut().enumerate() {
+                *byte = source.get(i).copied().unwrap_or(0);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn simulated_monitor() {
+        let simulated = SimulatedMonitor::new("DEL", 0xA0B1, 1234, "DELL U2720Q");
+        let monitor =
+            DdcMonitor::with_bus("i2c-4", simulated.clone()).delay(Duration::from_millis(0));
+        assert_eq!(monitor.edid().unwrap().name(), Some("DELL U2720Q"));
+        assert_eq!(monitor.id().unwrap(), "DEL-A0B1-1234");
+
+        simulated.fail_next(2);
+        assert_eq!(monitor.set_brightness_percent(30.0).unwrap(), 30);
+        assert_eq!(simulated.vcp(VCP_LUMINANCE), Some(30));
+        monitor.power_off().unwrap();
+        assert_eq!(monitor.power_state().unwrap(), PowerState::Powerdown);
+
+        simulated.fail_next(4);
+        assert!(monitor.brightness().is_err());
+    }
+}
@@ -0,0 +1,225 @@
+//! Errors returned by the library.
+
+use std::error;
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// A specialized `Result` type for operations on backlight devices.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// The error type for operations on backlight devices.
+///
+/// Every variant carries the path of the file or directory that caused it.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{Device, Error};
+/// let dev = Device::new("/nonexistent/backlight");
+/// match dev.brightness() {
+///     Err(Error::NotFound { path }) => assert!(path.ends_with("brightness")),
+///     other => panic!("unexpected result: {:?}", other),
+/// }
+/// ```
+#[derive(Debug)]
+pub enum Error {
+    /// The file exists, but the process is not allowed to access it
+    /// (usually because it is not running as root).
+    PermissionDenied {
+        /// The path that could not be accessed.
+        path: PathBuf,
+        /// The underlying I/O error.
+        source: io::Error,
+    },
+    /// The device, or one of its attributes, does not exist.
+    NotFound {
+        /// The path that does not exist.
+        path: PathBuf,
+    },
+    /// A value is well-formed but not acceptable, for instance a brightness
+    /// above `max_brightness`.
+    InvalidValue {
+        /// The path the value was read from or should have been written to.
+        path: PathBuf,
+        /// The offending value.
+        value: String,
+        /// A description of the accepted values.
+        expected: String,
+    },
+    /// The content of a file cannot be parsed.
+    Parse {
+        /// The path of the file.
+        path: PathBuf,
+        /// The content of the file, with surrounding whitespace trimmed.
+        content: String,
+        /// A description of what was expected.
+        expected: &'static str,
+    },
+    /// The device was built as read-only, so nothing is written.
+    ReadOnly {
+        /// The path that should have been written.
+        path: PathBuf,
+    },
+    /// The backlight does not support a setting, like a floor or a scale,
+    /// or the configuration file uses TOML beyond what is understood.
+    Unsupported {
+        /// The path of the backlight, or its name if it has no folder,
+        /// or the path of the configuration file.
+        path: PathBuf,
+        /// The setting or the syntax that is not supported.
+        setting: &'static str,
+    },
+    /// A path resolves outside of the directory it must stay in,
+    /// for instance through a symbolic link.
+    Untrusted {
+        /// The path as given.
+        path: PathBuf,
+        /// The path it resolves to.
+        target: PathBuf,
+    },
+    /// The daemon refused a request.
+    Daemon {
+        /// The path of the socket of the daemon.
+        path: PathBuf,
+        /// The message sent by the daemon.
+        message: String,
+    },
+    /// systemd-logind refused to set the brightness.
+    Logind {
+        /// The path of the socket of the bus.
+        path: PathBuf,
+        /// The D-Bus name of the error, like `org.freedesktop.DBus.Error.AccessDenied`.
+        name: String,
+        /// The message sent by logind.
+        message: String,
+    },
+    /// Any other I/O error.
+    Io {
+        /// The path that caused the error.
+        path: PathBuf,
+        /// The underlying I/O error.
+        source: io::Error,
+    },
+}
+
+impl Error {
+    /// Returns the path of the file or directory that caused the error.
+    pub fn path(&self) -> &Path {
+        match self {
+            Error::PermissionDenied { path, .. }
+            | Error::NotFound { path }
+            | Error::InvalidValue { path, .. }
+            | Error::Parse { path, .. }
+            | Error::ReadOnly { path }
+            | Error::Unsupported { path, .. }
+            | Error::Untrusted { path, .. }
+            | Error::Daemon { path, .. }
+            | Error::Logind { path, .. }
+            | Error::Io { path, .. } => path,
+        }
+    }
+
+    /// Wraps an I/O error that occurred while accessing `path`.
+    pub(crate) fn io<P: AsRef<Path>>(path: P, source: io::Error) -> Self {
+        let path = path.as_ref().to_path_buf();
+        match source.kind() {
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied { path, source },
+            io::ErrorKind::NotFound => Error::NotFound { path },
+            _ => Error::Io { path, source },
+        }
+    }
+
+    pub(crate) fn invalid_value<P, V, E>(path: P, value: V, expected: E) -> Self
+    where
+        P: AsRef<Path>,
+        V: ToString,
+        E: ToString,
+    {
+        Error::InvalidValue {
+            path: path.as_ref().to_path_buf(),
+            value: value.to_string(),
+            expected: expected.to_string(),
+        }
+    }
+
+    pub(crate) fn parse<P: AsRef<Path>>(path: P, content: &str, expected: &'static str) -> Self {
+        Error::Parse {
+            path: path.as_ref().to_path_buf(),
+            content: content.trim().to_string(),
+            expected,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::PermissionDenied { path, .. } => {
+                write!(f, "permission denied: {}", path.display())
+            }
+            Error::NotFound { path } => write!(f, "not found: {}", path.display()),
+            Error::InvalidValue {
+                path,
+                value,
+                expected,
+            } => write!(
+                f,
+                "invalid value '{}' for {}: expected {}",
+                value,
+                path.display(),
+                expected
+            ),
+            Error::Parse {
+                path,
+                content,
+                expected,
+            } => write!(
+                f,
+                "cannot parse '{}' in {}: expected {}",
+                content,
+                path.display(),
+                expected
+            ),
+            Error::ReadOnly { path } => write!(f, "read-only device: {}", path.display()),
+            Error::Unsupported { path, setting } => {
+                write!(f, "{} does not support {}", path.display(), setting)
+            }
+            Error::Untrusted { path, target } => write!(
+                f,
+                "untrusted path {}: it resolves to {}",
+                path.display(),
+                target.display()
+            ),
+            Error::Daemon { message, .. } => write!(f, "daemon: {}", message),
+            Error::Logind { name, message, .. } => write!(f, "logind: {}: {}", name, message),
+            Error::Io { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::PermissionDenied { source, .. } | Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> io::Error {
+        let kind = match &error {
+            Error::PermissionDenied { .. } => io::ErrorKind::PermissionDenied,
+            Error::NotFound { .. } => io::ErrorKind::NotFound,
+            Error::InvalidValue { .. } => io::ErrorKind::InvalidInput,
+            Error::Parse { .. } => io::ErrorKind::InvalidData,
+            Error::ReadOnly { .. } => io::ErrorKind::PermissionDenied,
+            Error::Unsupported { .. } => io::ErrorKind::Unsupported,
+            Error::Untrusted { .. } => io::ErrorKind::PermissionDenied,
+            Error::Daemon { .. } | Error::Logind { .. } => io::ErrorKind::Other,
+            Error::Io { source, .. } => source.kind(),
+        };
+        io::Error::new(kind, error)
+    }
+}
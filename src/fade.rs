@@ -0,0 +1,209 @@
+//! Smooth transitions of the brightness.
+
+use crate::backlight::attribute_path;
+use crate::{Backlight, Error, Result, BRIGHTNESS};
+use std::error;
+use std::f64::consts::PI;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The minimum interval between two writes during a fade.
+///
+/// It bounds the rate of the writes to about 60 per second.
+pub const FADE_INTERVAL: Duration = Duration::from_millis(16);
+
+/// The easing curve of a fade.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::Curve;
+/// assert_eq!("ease-in-out".parse(), Ok(Curve::EaseInOut));
+/// assert_eq!(Curve::Linear.apply(0.25), 0.25);
+/// assert!((Curve::EaseInOut.apply(0.5) - 0.5).abs() < 1e-9);
+/// assert!(Curve::EaseInOut.apply(0.1) < 0.1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum Curve {
+    /// The brightness changes at a constant rate.
+    #[default]
+    Linear,
+    /// The brightness changes slowly at the beginning and at the end.
+    EaseInOut,
+    /// The brightness changes by a constant ratio, which looks uniform to the eye.
+    Exponential,
+}
+
+impl Curve {
+    /// Maps the elapsed fraction `t` of the fade, in `0.0..=1.0`,
+    /// to the fraction of the brightness change.
+    ///
+    /// This ignores the [`Exponential`] curve, which depends on the endpoints
+    /// and is handled by [`Backlight::fade_to`].
+    ///
+    /// [`Exponential`]: #variant.Exponential
+    /// [`Backlight::fade_to`]: trait.Backlight.html#method.fade_to
+    pub fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Curve::Linear | Curve::Exponential => t,
+            Curve::EaseInOut => (1.0 - (PI * t).cos()) / 2.0,
+        }
+    }
+
+    /// Returns the name of the curve, as accepted by [`FromStr`].
+    ///
+    /// [`FromStr`]: https://doc.rust-lang.org/stable/std/str/trait.FromStr.html
+    pub fn name(self) -> &'static str {
+        match self {
+            Curve::Linear => "linear",
+            Curve::EaseInOut => "ease-in-out",
+            Curve::Exponential => "exponential",
+        }
+    }
+
+    /// Interpolates between `start` and `end` at the elapsed fraction `t`.
+    fn interpolate(self, start: i32, end: i32, t: f64) -> i32 {
+        let (start, end) = (f64::from(start), f64::from(end));
+        let value = match self {
+            // Shift by one so that the ratio is defined when an endpoint is zero.
+            Curve::Exponential => (start + 1.0) * ((end + 1.0) / (start + 1.0)).powf(t) - 1.0,
+            _ => start + (end - start) * self.apply(t),
+        };
+        value.round() as i32
+    }
+}
+
+impl fmt::Display for Curve {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Curve {
+    type Err = ParseCurveError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "linear" => Ok(Curve::Linear),
+            "ease-in-out" | "ease_in_out" | "ease" => Ok(Curve::EaseInOut),
+            "exponential" | "exp" => Ok(Curve::Exponential),
+            _ => Err(ParseCurveError(s.to_string())),
+        }
+    }
+}
+
+/// The error returned when a [`Curve`] cannot be parsed.
+///
+/// [`Curve`]: enum.Curve.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCurveError(String);
+
+impl fmt::Display for ParseCurveError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid curve '{}'", self.0)
+    }
+}
+
+impl error::Error for ParseCurveError {}
+
+/// Runs the fade of [`Backlight::fade_to_until`].
+///
+/// [`Backlight::fade_to_until`]: trait.Backlight.html#method.fade_to_until
+pub(crate) fn fade_to_until<B: Backlight + ?Sized>(
+    device: &B,
+    target: i32,
+    duration: Duration,
+    curve: Curve,
+    stop: &AtomicBool,
+) -> Result<i32> {
+    let min = device.min_brightness()?;
+    let max = device.ceiling_brightness()?;
+    fade_within(device, target, min, max, duration, curve, stop)
+}
+
+/// Runs the fade of [`Backlight::force_fade_to_until`].
+///
+/// [`Backlight::force_fade_to_until`]: trait.Backlight.html#method.force_fade_to_until
+pub(crate) fn force_fade_to_until<B: Backlight + ?Sized>(
+    device: &B,
+    target: i32,
+    duration: Duration,
+    curve: Curve,
+    stop: &AtomicBool,
+) -> Result<i32> {
+    let max = device.max_brightness()?;
+    fade_within(device, target, 0, max, duration, curve, stop)
+}
+
+/// Fades `device` to `target`, which must lie in `min..=max`.
+fn fade_within<B: Backlight + ?Sized>(
+    device: &B,
+    target: i32,
+    min: i32,
+    max: i32,
+    duration: Duration,
+    curve: Curve,
+    stop: &AtomicBool,
+) -> Result<i32> {
+    if target < min || target > max {
+        return Err(Error::invalid_value(
+            attribute_path(device, BRIGHTNESS),
+            target,
+            format!("{}..={}", min, max),
+        ));
+    }
+
+    let start = device.brightness()?;
+    let reached = fade_all_until(&[(device, start, target)], duration, curve, stop)?;
+    Ok(reached[0])
+}
+
+/// Fades each of `fades`, given as a device, a start and a target, at the
+/// same pace, and returns the brightness reached by each device.
+///
+/// The targets are not checked against the limits of the devices.
+pub(crate) fn fade_all_until<B: Backlight + ?Sized>(
+    fades: &[(&B, i32, i32)],
+    duration: Duration,
+    curve: Curve,
+    stop: &AtomicBool,
+) -> Result<Vec<i32>> {
+    let start = Instant::now();
+    let mut current: Vec<i32> = fades.iter().map(|&(_, start, _)| start).collect();
+    let done = |current: &[i32]| {
+        fades
+            .iter()
+            .zip(current)
+            .all(|(&(_, _, target), &value)| value == target)
+    };
+    while !done(&current) {
+        if stop.load(Ordering::SeqCst) {
+            return Ok(current);
+        }
+        let elapsed = start.elapsed();
+        for (&(device, start_value, target), current) in fades.iter().zip(&mut current) {
+            let value = if elapsed >= duration {
+                target
+            } else {
+                let t = elapsed.as_secs_f64() / duration.as_secs_f64();
+                curve.interpolate(start_value, target, t)
+            };
+            if value != *current {
+                // The intermediate values lie between the start and the target,
+                // so they are only below the floor if the start already is.
+                device.force_brightness(value)?;
+                *current = value;
+            }
+        }
+        if !done(&current) {
+            thread::sleep(FADE_INTERVAL);
+        }
+    }
+    Ok(current)
+}
@@ -0,0 +1,68 @@
+//! Clean stops of the long-running operations on SIGINT and SIGTERM.
+
+use crate::sys::{self, SIGINT, SIGTERM};
+use std::os::raw::c_int;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Raised by the signal handler.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_signal(_: c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Catches SIGINT and SIGTERM until the returned guard is dropped.
+///
+/// Instead of ending the process, the signals raise the [`flag`] of the
+/// guard, to be passed to the operations that stop when it is raised, like
+/// [`Backlight::fade_to_until`] or [`Watcher::until`]. Only wrap such
+/// operations, since any other one would just ignore the signals. The flag
+/// is lowered when the guard is created, and dropping the guard restores
+/// the default action of the signals.
+///
+/// [`flag`]: struct.InterruptGuard.html#method.flag
+/// [`Backlight::fade_to_until`]: trait.Backlight.html#method.fade_to_until
+/// [`Watcher::until`]: struct.Watcher.html#method.until
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::catch_interrupts;
+/// use std::sync::atomic::Ordering;
+/// extern "C" {
+///     fn raise(signum: i32) -> i32;
+/// }
+///
+/// let interrupt = catch_interrupts();
+/// assert!(!interrupt.flag().load(Ordering::SeqCst));
+/// unsafe { raise(2) };
+/// assert!(interrupt.flag().load(Ordering::SeqCst));
+/// ```
+pub fn catch_interrupts() -> InterruptGuard {
+    INTERRUPTED.store(false, Ordering::SeqCst);
+    sys::set_signal_handler(SIGINT, on_signal);
+    sys::set_signal_handler(SIGTERM, on_signal);
+    InterruptGuard { _private: () }
+}
+
+/// The guard returned by [`catch_interrupts`].
+///
+/// [`catch_interrupts`]: fn.catch_interrupts.html
+#[derive(Debug)]
+pub struct InterruptGuard {
+    _private: (),
+}
+
+impl InterruptGuard {
+    /// Returns the flag raised by SIGINT and SIGTERM.
+    pub fn flag(&self) -> &'static AtomicBool {
+        &INTERRUPTED
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        sys::reset_signal_handler(SIGINT);
+        sys::reset_signal_handler(SIGTERM);
+    }
+}
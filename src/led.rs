@@ -0,0 +1,437 @@
+//! LED-class devices, like keyboard backlights.
+
+use crate::access::Access;
+use crate::secure::{self, OpenFiles};
+use crate::{
+    logind, raw_limits, stable_id, Backlight, Error, Level, PowerState, Result, Scale, BRIGHTNESS,
+    MAX_BRIGHTNESS,
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The default directory where to look for LEDs.
+///
+/// The value under Linux is `"/sys/class/leds"`.
+pub const LEDS_PATH: &str = "/sys/class/leds";
+
+/// The name of the file selecting the trigger of a LED.
+///
+/// The value under Linux is `"trigger"`.
+pub const TRIGGER: &str = "trigger";
+
+/// The name of the file holding the last brightness set by the hardware,
+/// for instance by a keyboard hotkey handled by the firmware.
+///
+/// The value under Linux is `"brightness_hw_changed"`.
+pub const BRIGHTNESS_HW_CHANGED: &str = "brightness_hw_changed";
+
+/// The suffix of the names of the keyboard backlights.
+pub const KBD_BACKLIGHT_SUFFIX: &str = "::kbd_backlight";
+
+/// A LED-class device, like a keyboard backlight.
+///
+/// LEDs have the same `brightness` and `max_brightness` attributes as
+/// backlight devices, but no `bl_power`: they are OFF at brightness 0.
+/// As a [`Backlight`], powering a LED ON sets its [`ceiling_brightness`] if
+/// it is OFF, and its [`actual_brightness`] is [`BRIGHTNESS_HW_CHANGED`].
+///
+/// Like a [`Device`], a LED has a floor, a ceiling and a scale, and can fall
+/// back on logind when writing `brightness` is not permitted. The floor is
+/// `Level::Raw(0)` by default, since a LED is merely OFF at 0.
+///
+/// [`Backlight`]: trait.Backlight.html
+/// [`ceiling_brightness`]: #method.ceiling_brightness
+/// [`actual_brightness`]: trait.Backlight.html#method.actual_brightness
+/// [`BRIGHTNESS_HW_CHANGED`]: constant.BRIGHTNESS_HW_CHANGED.html
+/// [`Device`]: struct.Device.html
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{Backlight, Led, Level, PowerState};
+/// # use std::fs;
+/// let path = std::env::temp_dir().join("rust-lcd-doc-led").join("dell::kbd_backlight");
+/// # fs::create_dir_all(&path).unwrap();
+/// fs::write(path.join("max_brightness"), "2\n").unwrap();
+/// fs::write(path.join("brightness"), "0\n").unwrap();
+/// fs::write(path.join("trigger"), "none [kbd-backlight] timer\n").unwrap();
+/// let led = Led::new(&path);
+/// assert!(led.is_keyboard());
+/// assert_eq!(led.power_state().unwrap(), PowerState::Powerdown);
+/// assert_eq!(led.toggle().unwrap(), PowerState::Unblank);
+/// assert_eq!(led.brightness().unwrap(), 2);
+/// assert_eq!(led.trigger().unwrap().as_deref(), Some("kbd-backlight"));
+/// assert!(led.set_trigger("heartbeat").is_err());
+///
+/// let mut led = led;
+/// led.set_ceiling(Some(Level::Raw(1)));
+/// assert!(led.set_brightness(2).is_err());
+/// led.power_off().unwrap();
+/// led.power_on().unwrap();
+/// assert_eq!(led.brightness().unwrap(), 1);
+/// # fs::remove_dir_all(path.parent().unwrap()).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Led {
+    path: PathBuf,
+    scale: Scale,
+    floor: Level,
+    ceiling: Option<Level>,
+    /// The attribute files opened by `open_within`, used instead of the paths.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    files: Option<Arc<OpenFiles>>,
+    /// The logind session setting the brightness when writing is not permitted.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    logind: Option<Arc<logind::Session>>,
+    via_logind: bool,
+}
+
+impl Led {
+    /// Creates a new LED located at `path`.
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        Self {
+            path: path.as_ref().to_path_buf(),
+            scale: Scale::Linear,
+            floor: Level::Raw(0),
+            ceiling: None,
+            files: None,
+            logind: None,
+            via_logind: false,
+        }
+    }
+
+    /// Sets the logind session through which the brightness is set when
+    /// writing `brightness` fails with [`Error::PermissionDenied`], like
+    /// [`Session::system`]. There is none by default.
+    ///
+    /// [`Error::PermissionDenied`]: enum.Error.html#variant.PermissionDenied
+    /// [`Session::system`]: logind/struct.Session.html#method.system
+    pub fn logind(mut self, session: Option<logind::Session>) -> Self {
+        self.logind = session.map(Arc::new);
+        self
+    }
+
+    /// Makes the LED set the brightness through logind directly,
+    /// without trying to write `brightness` first.
+    pub fn via_logind(mut self, via_logind: bool) -> Self {
+        self.via_logind = via_logind;
+        self
+    }
+
+    /// Returns the path of the LED.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the name of the LED, like `dell::kbd_backlight`.
+    pub fn name(&self) -> &str {
+        self.path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("")
+    }
+
+    /// Returns `true` if the LED is a keyboard backlight,
+    /// according to its name.
+    pub fn is_keyboard(&self) -> bool {
+        self.name().ends_with(KBD_BACKLIGHT_SUFFIX)
+    }
+
+    /// Returns the scale used to convert percentages to raw values.
+    pub fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    /// Sets the scale used to convert percentages to raw values.
+    pub fn set_scale(&mut self, scale: Scale) {
+        self.scale = scale;
+    }
+
+    /// Returns the minimum brightness that set and step operations respect.
+    pub fn floor(&self) -> Level {
+        self.floor
+    }
+
+    /// Sets the minimum brightness that set and step operations respect.
+    pub fn set_floor(&mut self, floor: Level) {
+        self.floor = floor;
+    }
+
+    /// Returns the maximum brightness that set and step operations respect,
+    /// if any.
+    pub fn ceiling(&self) -> Option<Level> {
+        self.ceiling
+    }
+
+    /// Sets the maximum brightness that set and step operations respect.
+    ///
+    /// Setting it to `None` disables the ceiling.
+    pub fn set_ceiling(&mut self, ceiling: Option<Level>) {
+        self.ceiling = ceiling;
+    }
+
+    /// Reads the current brightness of the LED.
+    pub fn brightness(&self) -> Result<i32> {
+        self.access().read_i32(&self.path.join(BRIGHTNESS))
+    }
+
+    /// Reads the maximum brightness of the LED.
+    pub fn max_brightness(&self) -> Result<i32> {
+        self.access().read_i32(&self.path.join(MAX_BRIGHTNESS))
+    }
+
+    /// Reads the last brightness set by the hardware.
+    ///
+    /// The kernel notifies changes of this attribute, so that a [`Watcher`]
+    /// reports them immediately.
+    ///
+    /// [`Watcher`]: struct.Watcher.html
+    pub fn brightness_hw_changed(&self) -> Result<i32> {
+        self.access()
+            .read_i32(&self.path.join(BRIGHTNESS_HW_CHANGED))
+    }
+
+    /// Returns the raw value of the [`floor`](#method.floor),
+    /// which is never above `max_brightness`.
+    pub fn min_brightness(&self) -> Result<i32> {
+        Ok(raw_limits(self, self.floor, None)?.0)
+    }
+
+    /// Returns the raw value of the [`ceiling`](#method.ceiling), which is
+    /// `max_brightness` if there is none, and never below `min_brightness`.
+    pub fn ceiling_brightness(&self) -> Result<i32> {
+        Ok(raw_limits(self, self.floor, self.ceiling)?.1)
+    }
+
+    /// Sets the brightness of the LED to the raw `value`, which must lie in
+    /// `min_brightness..=ceiling_brightness`.
+    ///
+    /// Note that the kernel disables the trigger when the brightness is set to 0.
+    pub fn set_brightness(&self, value: i32) -> Result<()> {
+        self.write_brightness(value, self.min_brightness()?, self.ceiling_brightness()?)
+    }
+
+    /// Sets the brightness of the LED to the raw `value`, ignoring the
+    /// floor and the ceiling; it must lie in `0..=max_brightness`.
+    pub fn force_brightness(&self, value: i32) -> Result<()> {
+        self.write_brightness(value, 0, self.max_brightness()?)
+    }
+
+    fn write_brightness(&self, value: i32, min: i32, max: i32) -> Result<()> {
+        let device = (logind::LEDS_SUBSYSTEM, self.name());
+        self.access()
+            .write_brightness(&self.path.join(BRIGHTNESS), device, value, (min, max))
+    }
+
+    /// Reads the triggers available for the LED.
+    pub fn triggers(&self) -> Result<Vec<String>> {
+        let content = self.access().read_string(&self.path.join(TRIGGER))?;
+        Ok(content
+            .split_whitespace()
+            .map(|trigger| trigger.trim_matches(|c| c == '[' || c == ']').to_string())
+            .collect())
+    }
+
+    /// Reads the active trigger of the LED, `None` if it is `none`.
+    pub fn trigger(&self) -> Result<Option<String>> {
+        let path = self.path.join(TRIGGER);
+        let content = self.access().read_string(&path)?;
+        let active = content
+            .split_whitespace()
+            .find(|trigger| trigger.starts_with('[') && trigger.ends_with(']'))
+            .map(|trigger| trigger[1..trigger.len() - 1].to_string())
+            .ok_or_else(|| Error::parse(&path, &content, "a list with an active trigger"))?;
+        Ok(Some(active).filter(|active| active != "none"))
+    }
+
+    /// Activates the trigger named `trigger`, which must be one of [`triggers`].
+    ///
+    /// Use `none` to drive the LED by its brightness alone.
+    ///
+    /// [`triggers`]: #method.triggers
+    pub fn set_trigger(&self, trigger: &str) -> Result<()> {
+        let path = self.path.join(TRIGGER);
+        let triggers = self.triggers()?;
+        if !triggers.iter().any(|known| known == trigger) {
+            return Err(Error::invalid_value(path, trigger, triggers.join(", ")));
+        }
+        self.access().write(&path, trigger)
+    }
+
+    /// Resolves the LED and opens its attribute files, making sure that
+    /// they all lie inside `root`.
+    ///
+    /// See [`Device::open_within`] for the details.
+    ///
+    /// [`Device::open_within`]: struct.Device.html#method.open_within
+    pub fn open_within<P: AsRef<Path>>(&self, root: P) -> Result<Led> {
+        let attributes = [
+            (self.path.join(BRIGHTNESS), true),
+            (self.path.join(MAX_BRIGHTNESS), false),
+            (self.path.join(BRIGHTNESS_HW_CHANGED), false),
+            (self.path.join(TRIGGER), true),
+        ];
+        let files = secure::open_files(&self.path, root.as_ref(), &attributes)?;
+        Ok(Led {
+            files: Some(Arc::new(files)),
+            ..self.clone()
+        })
+    }
+
+    fn access(&self) -> Access<'_> {
+        Access {
+            files: self.files.as_deref(),
+            logind: self.logind.as_deref(),
+            via_logind: self.via_logind,
+            read_only: false,
+        }
+    }
+}
+
+impl Backlight for Led {
+    fn name(&self) -> &str {
+        Led::name(self)
+    }
+
+    fn path(&self) -> Option<&Path> {
+        Some(Led::path(self))
+    }
+
+    fn id(&self) -> Result<String> {
+        stable_id(&self.path)
+    }
+
+    fn watched_paths(&self) -> Vec<PathBuf> {
+        vec![
+            self.path.join(BRIGHTNESS),
+            self.path.join(BRIGHTNESS_HW_CHANGED),
+        ]
+    }
+
+    fn power_state(&self) -> Result<PowerState> {
+        Ok(if Led::brightness(self)? > 0 {
+            PowerState::Unblank
+        } else {
+            PowerState::Powerdown
+        })
+    }
+
+    fn set_power_state(&self, state: PowerState) -> Result<()> {
+        if !state.is_on() {
+            self.force_brightness(0)
+        } else if Led::brightness(self)? == 0 {
+            Led::set_brightness(self, self.ceiling_brightness()?)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn brightness(&self) -> Result<i32> {
+        Led::brightness(self)
+    }
+
+    fn actual_brightness(&self) -> Result<i32> {
+        self.brightness_hw_changed()
+    }
+
+    fn max_brightness(&self) -> Result<i32> {
+        Led::max_brightness(self)
+    }
+
+    fn min_brightness(&self) -> Result<i32> {
+        Led::min_brightness(self)
+    }
+
+    fn ceiling_brightness(&self) -> Result<i32> {
+        Led::ceiling_brightness(self)
+    }
+
+    fn set_brightness(&self, value: i32) -> Result<()> {
+        Led::set_brightness(self, value)
+    }
+
+    fn force_brightness(&self, value: i32) -> Result<()> {
+        Led::force_brightness(self, value)
+    }
+
+    fn scale(&self) -> Scale {
+        Led::scale(self)
+    }
+
+    fn set_floor(&mut self, floor: Level) -> Result<()> {
+        Led::set_floor(self, floor);
+        Ok(())
+    }
+
+    fn set_ceiling(&mut self, ceiling: Option<Level>) -> Result<()> {
+        Led::set_ceiling(self, ceiling);
+        Ok(())
+    }
+
+    fn set_scale(&mut self, scale: Scale) -> Result<()> {
+        Led::set_scale(self, scale);
+        Ok(())
+    }
+}
+
+/// An iterator over the LEDs found in a given folder, [`LEDS_PATH`] by default.
+///
+/// Every entry with a `brightness` attribute is a LED; the errors reading
+/// the folder are returned.
+///
+/// [`LEDS_PATH`]: constant.LEDS_PATH.html
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::LedIter;
+/// # use std::fs;
+/// let root = std::env::temp_dir().join("rust-lcd-doc-leds");
+/// for name in &["dell::kbd_backlight", "input3::capslock"] {
+///     fs::create_dir_all(root.join(name)).unwrap();
+///     fs::write(root.join(name).join("brightness"), "0\n").unwrap();
+/// }
+/// let keyboards: Vec<_> = LedIter::new(&root)
+///     .unwrap()
+///     .filter_map(Result::ok)
+///     .filter(|led| led.is_keyboard())
+///     .collect();
+/// assert_eq!(keyboards.len(), 1);
+/// assert_eq!(keyboards[0].name(), "dell::kbd_backlight");
+/// # fs::remove_dir_all(&root).unwrap();
+/// ```
+pub struct LedIter {
+    dir: PathBuf,
+    readdir: fs::ReadDir,
+}
+
+impl LedIter {
+    /// Creates an iterator over the LEDs found in `path`,
+    /// or returns the error reading it.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let dir = path.as_ref().to_path_buf();
+        let readdir = fs::read_dir(&dir).map_err(|e| Error::io(&dir, e))?;
+        Ok(Self { dir, readdir })
+    }
+}
+
+impl Iterator for LedIter {
+    type Item = Result<Led>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for entry in &mut self.readdir {
+            match entry {
+                Ok(entry) => {
+                    let led = Led::new(entry.path());
+                    if led.path.join(BRIGHTNESS).is_file() {
+                        return Some(Ok(led));
+                    }
+                }
+                Err(e) => return Some(Err(Error::io(&self.dir, e))),
+            }
+        }
+        None
+    }
+}
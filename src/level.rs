@@ -0,0 +1,81 @@
+//! Absolute brightness levels.
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// An absolute brightness level, either raw or relative to the maximum.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::Level;
+/// assert_eq!("40%".parse(), Ok(Level::Percent(40.0)));
+/// assert_eq!("12".parse(), Ok(Level::Raw(12)));
+/// assert_eq!(Level::Percent(2.5).to_string(), "2.5%");
+/// assert!("-3".parse::<Level>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Level {
+    /// A raw brightness value.
+    Raw(i32),
+    /// A percentage of the maximum brightness, according to the [`Scale`] of the device.
+    ///
+    /// [`Scale`]: enum.Scale.html
+    Percent(f64),
+}
+
+impl Default for Level {
+    fn default() -> Self {
+        Level::Raw(0)
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Raw(value) => write!(f, "{}", value),
+            Level::Percent(percent) => write!(f, "{}%", percent),
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = ParseLevelError;
+
+    /// Parses a raw value like `12` or a percentage like `40%`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseLevelError(s.to_string());
+        let t = s.trim();
+        match t.strip_suffix('%') {
+            Some(percent) => percent
+                .parse::<f64>()
+                .ok()
+                .filter(|percent| (0.0..=100.0).contains(percent))
+                .map(Level::Percent)
+                .ok_or_else(err),
+            None => t
+                .parse::<i32>()
+                .ok()
+                .filter(|value| *value >= 0)
+                .map(Level::Raw)
+                .ok_or_else(err),
+        }
+    }
+}
+
+/// The error returned when a [`Level`] cannot be parsed.
+///
+/// [`Level`]: enum.Level.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseLevelError(String);
+
+impl fmt::Display for ParseLevelError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid brightness level '{}'", self.0)
+    }
+}
+
+impl error::Error for ParseLevelError {}
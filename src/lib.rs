@@ -19,12 +19,67 @@
 //!         device.path().parent().unwrap());
 //! }
 //! ```
+//!
+//! # Features
+//!
+//! - `serde`: implements `Serialize` and `Deserialize` for [`Device`],
+//!   [`DeviceSnapshot`], [`PowerState`], [`State`] and the other value types.
+//!
+//! [`Device`]: struct.Device.html
+//! [`DeviceSnapshot`]: struct.DeviceSnapshot.html
+//! [`PowerState`]: enum.PowerState.html
+//! [`State`]: struct.State.html
 
 #![deny(missing_docs)]
 
+use access::Access;
+use std::convert::TryFrom;
 use std::fs;
-use std::io;
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+mod access;
+mod backlight;
+mod backlight_type;
+mod config;
+pub mod daemon;
+pub mod ddc;
+mod error;
+mod fade;
+mod interrupt;
+mod led;
+mod level;
+pub mod logind;
+mod mock;
+mod monitor;
+mod power;
+mod scale;
+mod scene;
+mod secure;
+mod snapshot;
+mod state;
+mod step;
+mod sys;
+mod watch;
+
+pub use backlight::Backlight;
+pub use backlight_type::{BacklightType, ParseBacklightTypeError};
+pub use config::{user_config_path, Config, Settings, SYSTEM_CONFIG_PATH};
+pub use error::{Error, Result};
+pub use fade::{Curve, ParseCurveError, FADE_INTERVAL};
+pub use interrupt::{catch_interrupts, InterruptGuard};
+pub use led::{Led, LedIter, BRIGHTNESS_HW_CHANGED, KBD_BACKLIGHT_SUFFIX, LEDS_PATH, TRIGGER};
+pub use level::{Level, ParseLevelError};
+pub use mock::MockBacklight;
+pub use monitor::{DeviceMonitor, HotplugEvent, MonitorBackend, MONITOR_INTERVAL};
+pub use power::{ParsePowerStateError, PowerState};
+pub use scale::{ParseScaleError, Scale};
+pub use scene::{ParseSceneTargetError, Scene, SceneTarget};
+pub use secure::{drop_privileges, is_setuid, sanitize_environment, SYSFS_DEVICES_PATH};
+pub use snapshot::DeviceSnapshot;
+pub use state::{default_state_path, SavedDevice, State, SYSTEM_STATE_PATH};
+pub use step::{ParseStepError, Step};
+pub use watch::{Attribute, ChangeEvent, WatchBackend, Watcher, WATCH_INTERVAL};
 
 /// The default directory where to look for devices.
 ///
@@ -36,48 +91,79 @@ pub const BACKLIGHT_PATH: &str = "/sys/class/backlight";
 /// The value under Linux is `"bl_power"`.
 pub const BL_POWER: &str = "bl_power";
 
+/// The default name of the file holding the requested brightness of the device.
+///
+/// The value under Linux is `"brightness"`.
+pub const BRIGHTNESS: &str = "brightness";
+
+/// The default name of the file holding the brightness actually reported by the hardware.
+///
+/// The value under Linux is `"actual_brightness"`.
+pub const ACTUAL_BRIGHTNESS: &str = "actual_brightness";
+
+/// The default name of the file holding the maximum brightness of the device.
+///
+/// The value under Linux is `"max_brightness"`.
+pub const MAX_BRIGHTNESS: &str = "max_brightness";
+
+/// The default name of the file holding the type of the device.
+///
+/// The value under Linux is `"type"`.
+pub const TYPE: &str = "type";
+
+/// The default minimum brightness of the devices, see [`DeviceBuilder::floor`].
+///
+/// It keeps a plain `brightness 0` from blacking out the screen, since a
+/// brightness of 0 turns the backlight off on many panels.
+///
+/// [`DeviceBuilder::floor`]: struct.DeviceBuilder.html#method.floor
+pub const DEFAULT_FLOOR: Level = Level::Raw(1);
+
 /// A single backlight device that can be toggled ON and OFF.
 ///
+/// Toggling it and the other operations built on its attributes are the
+/// provided methods of [`Backlight`], which must be in scope.
+///
 /// # Examples
 ///
 /// ```
-/// # use rust_lcd::{BL_POWER, Device};
+/// # use rust_lcd::{Backlight, BL_POWER, Device};
 /// use std::path::Path;
 /// let path = Path::new("/sys/class/backlight/intel_backlight");
 /// let dev = Device::new(path);
 /// assert_eq!(dev.bl_power(), path.join(BL_POWER));
 /// assert!(dev.toggle().is_err()); // we don't have permission
 /// ```
-#[derive(Debug)]
+///
+/// [`Backlight`]: trait.Backlight.html
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Device {
     path: PathBuf,
     bl_power: PathBuf,
+    brightness: PathBuf,
+    max_brightness: PathBuf,
+    read_only: bool,
+    scale: Scale,
+    floor: Level,
+    ceiling: Option<Level>,
+    /// The attribute files opened by `open_within`, used instead of the paths.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    files: Option<Arc<secure::OpenFiles>>,
+    /// The logind session setting the brightness when writing is not permitted.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    logind: Option<Arc<logind::Session>>,
+    via_logind: bool,
 }
 
 impl Device {
-    /// Creates a new device located at `path`.
+    /// Creates a new device located at `path`, with the default file names.
+    ///
+    /// Use a [`DeviceBuilder`] to customize them.
+    ///
+    /// [`DeviceBuilder`]: struct.DeviceBuilder.html
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
-        let path: &Path = path.as_ref();
-        Self {
-            path: path.to_path_buf(),
-            bl_power: path.join(BL_POWER),
-        }
-    }
-
-    // This is not exposed in the public API at the moment.
-    #[allow(unused)]
-    fn custom<P: AsRef<Path>, Q: AsRef<Path>>(path: P, bl_power: Option<Q>) -> Self {
-        let path: &Path = path.as_ref();
-        match bl_power {
-            Some(q) => Device {
-                path: path.to_path_buf(),
-                bl_power: path.join(q),
-            },
-            None => Device {
-                path: path.to_path_buf(),
-                bl_power: path.join(BL_POWER),
-            },
-        }
+        DeviceBuilder::new().build(path)
     }
 
     /// Returns the path of the device.
@@ -85,37 +171,414 @@ impl Device {
         &self.path
     }
 
+    /// Returns the name of the device, i.e. the last component of its path.
+    pub fn name(&self) -> &str {
+        self.path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or("")
+    }
+
+    /// Returns a stable identifier of the device.
+    ///
+    /// The entries of [`BACKLIGHT_PATH`] are symbolic links into `/sys/devices`,
+    /// whose targets depend on the position of the device on the bus rather
+    /// than on the order of discovery. The identifier is the resolved path,
+    /// relative to `/sys` when it lies inside it.
+    ///
+    /// [`BACKLIGHT_PATH`]: constant.BACKLIGHT_PATH.html
+    pub fn stable_id(&self) -> Result<String> {
+        stable_id(&self.path)
+    }
+
     /// Returns the path of the device power controller.
     pub fn bl_power(&self) -> &Path {
         &self.bl_power
     }
 
-    /// Toggles the state of the device ON and OFF.
+    /// Returns `true` if the device refuses every write.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
+    /// Returns the scale used to convert percentages to raw values.
+    pub fn scale(&self) -> Scale {
+        self.scale
+    }
+
+    /// Sets the scale used to convert percentages to raw values.
+    pub fn set_scale(&mut self, scale: Scale) {
+        self.scale = scale;
+    }
+
+    /// Returns the minimum brightness that set and step operations respect.
+    pub fn floor(&self) -> Level {
+        self.floor
+    }
+
+    /// Sets the minimum brightness that set and step operations respect.
+    ///
+    /// Setting it to `Level::Raw(0)` disables the floor.
+    pub fn set_floor(&mut self, floor: Level) {
+        self.floor = floor;
+    }
+
+    /// Returns the maximum brightness that set and step operations respect,
+    /// if any.
+    pub fn ceiling(&self) -> Option<Level> {
+        self.ceiling
+    }
+
+    /// Sets the maximum brightness that set and step operations respect.
+    ///
+    /// Setting it to `None` disables the ceiling.
+    pub fn set_ceiling(&mut self, ceiling: Option<Level>) {
+        self.ceiling = ceiling;
+    }
+
+    /// Reads the type of the device.
+    pub fn backlight_type(&self) -> Result<BacklightType> {
+        let path = self.path.join(TYPE);
+        let content = self.access().read_string(&path)?;
+        content
+            .parse()
+            .map_err(|_| Error::parse(&path, &content, "raw, platform or firmware"))
+    }
+
+    /// Reads the current brightness of the device.
+    ///
+    /// This is the value last requested through the `brightness` file,
+    /// which may differ from [`actual_brightness`](#method.actual_brightness).
+    pub fn brightness(&self) -> Result<i32> {
+        self.access().read_i32(&self.brightness)
+    }
+
+    /// Reads the brightness actually reported by the hardware.
+    pub fn actual_brightness(&self) -> Result<i32> {
+        self.access().read_i32(&self.path.join(ACTUAL_BRIGHTNESS))
+    }
+
+    /// Reads the maximum brightness supported by the device.
+    pub fn max_brightness(&self) -> Result<i32> {
+        self.access().read_i32(&self.max_brightness)
+    }
+
+    /// Returns the raw value of the [`floor`](#method.floor),
+    /// which is never above `max_brightness`.
+    pub fn min_brightness(&self) -> Result<i32> {
+        Ok(raw_limits(self, self.floor, None)?.0)
+    }
+
+    /// Returns the raw value of the [`ceiling`](#method.ceiling), which is
+    /// `max_brightness` if there is none, and never below `min_brightness`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_lcd::{Backlight, DeviceBuilder, Level, Step};
+    /// # use std::fs;
+    /// let path = std::env::temp_dir().join("rust-lcd-doc-ceiling");
+    /// # fs::create_dir_all(&path).unwrap();
+    /// fs::write(path.join("max_brightness"), "200\n").unwrap();
+    /// fs::write(path.join("brightness"), "100\n").unwrap();
+    /// let dev = DeviceBuilder::new().ceiling(Level::Percent(80.0)).build(&path);
+    /// assert_eq!(dev.ceiling_brightness().unwrap(), 160);
+    /// assert!(dev.set_brightness(200).is_err());
+    /// assert_eq!(dev.set_brightness_percent(100.0).unwrap(), 160);
+    /// assert_eq!(dev.step(Step::Raw(10)).unwrap(), 160);
+    /// dev.force_brightness(200).unwrap();
+    /// # fs::remove_dir_all(&path).unwrap();
+    /// ```
+    pub fn ceiling_brightness(&self) -> Result<i32> {
+        Ok(raw_limits(self, self.floor, self.ceiling)?.1)
+    }
+
+    /// Sets the brightness of the device to the raw `value`.
+    ///
+    /// The value must lie in `min_brightness..=ceiling_brightness`, otherwise
+    /// [`Error::InvalidValue`] is returned and nothing is written.
+    /// Use [`force_brightness`] to go beyond the floor or the ceiling.
+    ///
+    /// [`Error::InvalidValue`]: enum.Error.html#variant.InvalidValue
+    /// [`force_brightness`]: #method.force_brightness
+    pub fn set_brightness(&self, value: i32) -> Result<()> {
+        self.write_brightness(value, self.min_brightness()?, self.ceiling_brightness()?)
+    }
+
+    /// Sets the brightness of the device to the raw `value`, ignoring the
+    /// floor and the ceiling.
+    ///
+    /// The value must lie in `0..=max_brightness`, so this is the way to
+    /// turn the backlight completely off through `brightness`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_lcd::{Backlight, DeviceBuilder, Level};
+    /// # use std::fs;
+    /// let path = std::env::temp_dir().join("rust-lcd-doc-floor");
+    /// # fs::create_dir_all(&path).unwrap();
+    /// fs::write(path.join("max_brightness"), "200\n").unwrap();
+    /// fs::write(path.join("brightness"), "100\n").unwrap();
+    /// let dev = DeviceBuilder::new().floor(Level::Percent(5.0)).build(&path);
+    /// assert_eq!(dev.min_brightness().unwrap(), 10);
+    /// assert!(dev.set_brightness(0).is_err());
+    /// assert_eq!(dev.set_brightness_percent(0.0).unwrap(), 10);
+    /// dev.force_brightness(0).unwrap();
+    /// assert_eq!(dev.brightness().unwrap(), 0);
+    /// # fs::remove_dir_all(&path).unwrap();
+    /// ```
+    pub fn force_brightness(&self, value: i32) -> Result<()> {
+        self.write_brightness(value, 0, self.max_brightness()?)
+    }
+
+    fn write_brightness(&self, value: i32, min: i32, max: i32) -> Result<()> {
+        let device = (logind::BACKLIGHT_SUBSYSTEM, self.name());
+        self.access()
+            .write_brightness(&self.brightness, device, value, (min, max))
+    }
+
+    /// Reads the power state of the device.
+    pub fn power_state(&self) -> Result<PowerState> {
+        let value = self.access().read_i32(&self.bl_power)?;
+        PowerState::try_from(value)
+            .map_err(|_| Error::invalid_value(&self.bl_power, value, "0..=4"))
+    }
+
+    /// Sets the power state of the device.
+    pub fn set_power_state(&self, state: PowerState) -> Result<()> {
+        self.access().write_i32(&self.bl_power, state.into())
+    }
+
+    fn access(&self) -> Access<'_> {
+        Access {
+            files: self.files.as_deref(),
+            logind: self.logind.as_deref(),
+            via_logind: self.via_logind,
+            read_only: self.read_only,
+        }
+    }
+}
+
+/// A builder for [`Device`]s with custom file names.
+///
+/// Some drivers, as well as test fixtures, keep the attributes of the device
+/// under different names. The builder can be reused for many devices, and
+/// can be passed to [`DeviceIter::with_builder`] so that discovery applies
+/// the same overrides.
+///
+/// [`Device`]: struct.Device.html
+/// [`DeviceIter::with_builder`]: struct.DeviceIter.html#method.with_builder
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{Backlight, DeviceBuilder};
+/// use std::path::Path;
+/// let path = Path::new("/sys/class/backlight/vendor_backlight");
+/// let dev = DeviceBuilder::new()
+///     .bl_power("power")
+///     .read_only(true)
+///     .build(path);
+/// assert_eq!(dev.bl_power(), path.join("power"));
+/// assert!(dev.is_read_only());
+/// assert!(dev.power_on().is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct DeviceBuilder {
+    bl_power: PathBuf,
+    brightness: PathBuf,
+    max_brightness: PathBuf,
+    read_only: bool,
+    scale: Scale,
+    floor: Level,
+    ceiling: Option<Level>,
+    logind: Option<Arc<logind::Session>>,
+    via_logind: bool,
+}
+
+impl DeviceBuilder {
+    /// Creates a new builder with the default file names.
+    pub fn new() -> Self {
+        Self {
+            bl_power: PathBuf::from(BL_POWER),
+            brightness: PathBuf::from(BRIGHTNESS),
+            max_brightness: PathBuf::from(MAX_BRIGHTNESS),
+            read_only: false,
+            scale: Scale::Linear,
+            floor: DEFAULT_FLOOR,
+            ceiling: None,
+            logind: None,
+            via_logind: false,
+        }
+    }
+
+    /// Sets the name of the file controlling the power, [`BL_POWER`] by default.
     ///
-    /// The return value is either a [`std::io::Error`] or the new state of the device.
+    /// [`BL_POWER`]: constant.BL_POWER.html
+    pub fn bl_power<P: AsRef<Path>>(mut self, name: P) -> Self {
+        self.bl_power = name.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the name of the file holding the brightness, [`BRIGHTNESS`] by default.
+    ///
+    /// [`BRIGHTNESS`]: constant.BRIGHTNESS.html
+    pub fn brightness<P: AsRef<Path>>(mut self, name: P) -> Self {
+        self.brightness = name.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the name of the file holding the maximum brightness,
+    /// [`MAX_BRIGHTNESS`] by default.
     ///
-    /// [`std::io::Error`]: https://doc.rust-lang.org/stable/std/io/struct.Error.html
-    pub fn toggle(&self) -> io::Result<i32> {
-        let old_value = read_i32(&self.bl_power)?;
-        let new_value = if old_value == 0 { 1 } else { 0 };
-        write_i32(&self.bl_power, new_value)?;
-        Ok(new_value)
+    /// [`MAX_BRIGHTNESS`]: constant.MAX_BRIGHTNESS.html
+    pub fn max_brightness<P: AsRef<Path>>(mut self, name: P) -> Self {
+        self.max_brightness = name.as_ref().to_path_buf();
+        self
+    }
+
+    /// Sets the scale used to convert percentages to raw values,
+    /// [`Scale::Linear`] by default.
+    ///
+    /// [`Scale::Linear`]: enum.Scale.html#variant.Linear
+    pub fn scale(mut self, scale: Scale) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    /// Sets the minimum brightness that set and step operations respect,
+    /// [`DEFAULT_FLOOR`] by default; `Level::Raw(0)` disables the floor.
+    ///
+    /// [`DEFAULT_FLOOR`]: constant.DEFAULT_FLOOR.html
+    pub fn floor(mut self, floor: Level) -> Self {
+        self.floor = floor;
+        self
+    }
+
+    /// Sets the maximum brightness that set and step operations respect,
+    /// `max_brightness` by default.
+    pub fn ceiling(mut self, ceiling: Level) -> Self {
+        self.ceiling = Some(ceiling);
+        self
+    }
+
+    /// Makes the devices refuse every write with [`Error::ReadOnly`].
+    ///
+    /// [`Error::ReadOnly`]: enum.Error.html#variant.ReadOnly
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Sets the logind session through which the brightness is set when
+    /// writing `brightness` fails with [`Error::PermissionDenied`], like
+    /// [`Session::system`]. There is none by default.
+    ///
+    /// The devices built by the same builder share the connection to the bus.
+    ///
+    /// [`Error::PermissionDenied`]: enum.Error.html#variant.PermissionDenied
+    /// [`Session::system`]: logind/struct.Session.html#method.system
+    pub fn logind(mut self, session: Option<logind::Session>) -> Self {
+        self.logind = session.map(Arc::new);
+        self
+    }
+
+    /// Makes the devices set the brightness through logind directly,
+    /// without trying to write `brightness` first.
+    ///
+    /// The power state is always written to `bl_power`, since logind only
+    /// handles the brightness.
+    pub fn via_logind(mut self, via_logind: bool) -> Self {
+        self.via_logind = via_logind;
+        self
+    }
+
+    /// Builds the device located at `path`.
+    pub fn build<P: AsRef<Path>>(&self, path: P) -> Device {
+        let path: &Path = path.as_ref();
+        Device {
+            path: path.to_path_buf(),
+            bl_power: path.join(&self.bl_power),
+            brightness: path.join(&self.brightness),
+            max_brightness: path.join(&self.max_brightness),
+            read_only: self.read_only,
+            scale: self.scale,
+            floor: self.floor,
+            ceiling: self.ceiling,
+            files: None,
+            logind: self.logind.clone(),
+            via_logind: self.via_logind,
+        }
+    }
+}
+
+impl Default for DeviceBuilder {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 /// An iterator over the devices found in a given folder.
+///
+/// Errors are silently skipped: if the folder cannot be read the iterator
+/// is empty. Use [`DeviceIter::try_new`] to detect a missing folder and
+/// [`TryDeviceIter`] to also see the errors on the single entries.
+///
+/// [`DeviceIter::try_new`]: #method.try_new
+/// [`TryDeviceIter`]: struct.TryDeviceIter.html
 pub struct DeviceIter {
-    readdir: Option<fs::ReadDir>,
+    inner: Option<TryDeviceIter>,
 }
 
 impl DeviceIter {
     /// Create a new iterator over the devices found in `path`.
     pub fn new<P: AsRef<Path>>(path: P) -> Self {
-        let readdir = match fs::read_dir(&path) {
-            Ok(iter) => Some(iter),
-            Err(_) => None,
-        };
-        Self { readdir }
+        Self {
+            inner: TryDeviceIter::new(path).ok(),
+        }
+    }
+
+    /// Create a new iterator over the devices found in `path`,
+    /// failing if the folder cannot be read.
+    pub fn try_new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(Self {
+            inner: Some(TryDeviceIter::new(path)?),
+        })
+    }
+
+    /// Create a new iterator over the devices found in `path`,
+    /// built from the `builder` template.
+    ///
+    /// Only the entries containing the power file configured in `builder`
+    /// are considered devices.
+    pub fn with_builder<P: AsRef<Path>>(path: P, builder: DeviceBuilder) -> Self {
+        Self {
+            inner: TryDeviceIter::with_builder(path, builder).ok(),
+        }
+    }
+
+    /// Turns the iterator into one over [`Backlight`] trait objects,
+    /// so that the devices can be handled along with other backlights.
+    ///
+    /// [`Backlight`]: trait.Backlight.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_lcd::{Backlight, DeviceIter, MockBacklight};
+    /// # use std::fs;
+    /// let root = std::env::temp_dir().join("rust-lcd-doc-backlights");
+    /// # fs::create_dir_all(root.join("intel_backlight")).unwrap();
+    /// fs::write(root.join("intel_backlight").join("bl_power"), "0\n").unwrap();
+    /// let mut devices: Vec<Box<dyn Backlight>> = DeviceIter::new(&root).backlights().collect();
+    /// devices.push(Box::new(MockBacklight::new("external", 100)));
+    /// let names: Vec<&str> = devices.iter().map(|device| device.name()).collect();
+    /// assert_eq!(names, ["intel_backlight", "external"]);
+    /// # fs::remove_dir_all(&root).unwrap();
+    /// ```
+    pub fn backlights(self) -> impl Iterator<Item = Box<dyn Backlight>> {
+        self.map(<Box<dyn Backlight>>::from)
     }
 }
 
@@ -129,45 +592,158 @@ impl Iterator for DeviceIter {
     type Item = Device;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.readdir {
-            Some(ref mut diriter) => diriter
-                .filter(|entry| entry.is_ok())
-                .map(|entry| entry.unwrap().path())
-                .find(|path| bl_power(path).is_file())
-                .map(|p| Device::new(&p)),
-            _ => None,
+        self.inner.as_mut()?.find_map(|device| device.ok())
+    }
+}
+
+/// An iterator over the devices found in a given folder, reporting errors.
+///
+/// Each item is either a [`Device`] or the [`Error`] that occurred while
+/// reading the corresponding directory entry.
+///
+/// [`Device`]: struct.Device.html
+/// [`Error`]: enum.Error.html
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{Error, TryDeviceIter};
+/// match TryDeviceIter::new("/nonexistent/backlight") {
+///     Err(Error::NotFound { path }) => assert_eq!(path.to_str(), Some("/nonexistent/backlight")),
+///     _ => panic!("the folder should not exist"),
+/// }
+/// ```
+pub struct TryDeviceIter {
+    dir: PathBuf,
+    readdir: fs::ReadDir,
+    builder: DeviceBuilder,
+}
+
+impl TryDeviceIter {
+    /// Create a new iterator over the devices found in `path`,
+    /// failing if the folder cannot be read.
+    pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::with_builder(path, DeviceBuilder::new())
+    }
+
+    /// Create a new iterator over the devices found in `path`,
+    /// built from the `builder` template.
+    pub fn with_builder<P: AsRef<Path>>(path: P, builder: DeviceBuilder) -> Result<Self> {
+        let dir = path.as_ref().to_path_buf();
+        let readdir = fs::read_dir(&dir).map_err(|e| Error::io(&dir, e))?;
+        Ok(Self {
+            dir,
+            readdir,
+            builder,
+        })
+    }
+}
+
+impl Iterator for TryDeviceIter {
+    type Item = Result<Device>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for entry in &mut self.readdir {
+            match entry {
+                Ok(entry) => {
+                    let device = self.builder.build(entry.path());
+                    if device.bl_power().is_file() {
+                        return Some(Ok(device));
+                    }
+                }
+                Err(e) => return Some(Err(Error::io(&self.dir, e))),
+            }
         }
+        None
     }
 }
 
 /// Iterate over devices in `dir`.
 ///
-/// If successful, it returns an iterator over [`Device`]s.
+/// If successful, it returns an iterator over [`Device`]s,
+/// which skips the entries that cannot be read.
 ///
-/// The function can fail, returning `std::io::Error`,
+/// The function can fail, returning an [`Error`],
 /// if `std::fs::read_dir` cannot open the directory.
 ///
 /// [`Device`]: struct.Device.html
-pub fn iterate_devices<P: AsRef<Path>>(dir: P) -> io::Result<impl Iterator<Item = Device>> {
-    let diriter = fs::read_dir(dir)?;
-    Ok(diriter
-        .filter(|entry| entry.is_ok())
-        .map(|entry| entry.unwrap().path())
-        .filter(|path| bl_power(path).is_file())
-        .map(|p| Device::new(&p)))
+/// [`Error`]: enum.Error.html
+pub fn iterate_devices<P: AsRef<Path>>(dir: P) -> Result<impl Iterator<Item = Device>> {
+    DeviceIter::try_new(dir)
 }
 
-fn bl_power(path: &Path) -> PathBuf {
-    path.join(BL_POWER)
+/// Picks the preferred device among `devices`, like desktop environments do.
+///
+/// The native driver of the GPU is preferred over the firmware interface,
+/// which is often a stub on recent machines: the devices are ranked
+/// `raw`, then `platform`, then `firmware`, the reverse of the
+/// [`BacklightType`] order. Devices whose type cannot be read are only picked
+/// if there is nothing else. Ties are broken by name, so the result does not
+/// depend on the order of the directory entries.
+///
+/// [`BacklightType`]: enum.BacklightType.html
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{preferred_device, Device};
+/// # use std::fs;
+/// let root = std::env::temp_dir().join("rust-lcd-doc-preferred");
+/// for (name, kind) in &[("acpi_video0", "firmware"), ("intel_backlight", "raw")] {
+///     fs::create_dir_all(root.join(name)).unwrap();
+///     fs::write(root.join(name).join("type"), kind).unwrap();
+/// }
+/// let devices = vec![
+///     Device::new(root.join("intel_backlight")),
+///     Device::new(root.join("acpi_video0")),
+/// ];
+/// assert_eq!(preferred_device(devices).unwrap().name(), "intel_backlight");
+/// # fs::remove_dir_all(&root).unwrap();
+/// ```
+pub fn preferred_device<I, B>(devices: I) -> Option<B>
+where
+    I: IntoIterator<Item = B>,
+    B: Backlight,
+{
+    devices
+        .into_iter()
+        .map(|device| (device.backlight_type().ok(), device))
+        .min_by(|(a_type, a), (b_type, b)| {
+            (a_type.is_none(), a_type)
+                .cmp(&(b_type.is_none(), b_type))
+                .then_with(|| a.name().cmp(b.name()))
+        })
+        .map(|(_, device)| device)
+}
+
+/// Returns the raw value of `level` for `backlight`, according to its scale.
+pub(crate) fn level_to_raw<B: Backlight + ?Sized>(backlight: &B, level: Level) -> Result<i32> {
+    match level {
+        Level::Raw(value) => Ok(value),
+        Level::Percent(percent) => backlight.percent_to_raw(percent),
+    }
 }
 
-fn read_i32(path: &Path) -> io::Result<i32> {
-    fs::read_to_string(path)?
-        .trim()
-        .parse::<i32>()
-        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "cannot parse i32"))
+/// Returns the raw values of `floor` and `ceiling` for `backlight`: the
+/// floor lies in `0..=max_brightness`, and the ceiling, `max_brightness` if
+/// there is none, between the floor and `max_brightness`.
+pub(crate) fn raw_limits<B: Backlight + ?Sized>(
+    backlight: &B,
+    floor: Level,
+    ceiling: Option<Level>,
+) -> Result<(i32, i32)> {
+    let max = backlight.max_brightness()?;
+    let min = level_to_raw(backlight, floor)?.clamp(0, max);
+    let ceiling = match ceiling {
+        Some(ceiling) => level_to_raw(backlight, ceiling)?.clamp(min, max),
+        None => max,
+    };
+    Ok((min, ceiling))
 }
 
-fn write_i32(path: &Path, value: i32) -> io::Result<()> {
-    fs::write(path, value.to_string())
+/// Returns the resolved `path`, relative to `/sys` when it lies inside it.
+fn stable_id(path: &Path) -> Result<String> {
+    let resolved = fs::canonicalize(path).map_err(|e| Error::io(path, e))?;
+    let id = resolved.strip_prefix("/sys").unwrap_or(&resolved);
+    Ok(id.to_string_lossy().into_owned())
 }
@@ -0,0 +1,752 @@
+//! Brightness changes through systemd-logind.
+//!
+//! logind lets the owner of a session set the brightness of the backlight
+//! and LED devices of its seat, with the `SetBrightness` method of the
+//! `org.freedesktop.login1.Session` interface, so that no privileges are
+//! needed. [`Session`] is a minimal D-Bus client calling this method, and
+//! [`Device`] and [`Led`] fall back on it when writing `brightness` is not
+//! permitted.
+//!
+//! [`Session`]: struct.Session.html
+//! [`Device`]: ../struct.Device.html
+//! [`Led`]: ../struct.Led.html
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # use rust_lcd::logind::Session;
+//! # use rust_lcd::{Backlight, DeviceBuilder};
+//! let dev = DeviceBuilder::new()
+//!     .logind(Some(Session::system()))
+//!     .build("/sys/class/backlight/intel_backlight");
+//! dev.set_brightness(40).unwrap();
+//! ```
+
+use crate::{sys, Error, Result};
+use std::env;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// The default address of the system bus, where logind is found.
+pub const SYSTEM_BUS_ADDRESS: &str = "unix:path=/var/run/dbus/system_bus_socket";
+
+/// The environment variable overriding [`SYSTEM_BUS_ADDRESS`].
+///
+/// [`SYSTEM_BUS_ADDRESS`]: constant.SYSTEM_BUS_ADDRESS.html
+pub const SYSTEM_BUS_ADDRESS_VAR: &str = "DBUS_SYSTEM_BUS_ADDRESS";
+
+/// The subsystem of the backlight devices, as named by `SetBrightness`.
+pub const BACKLIGHT_SUBSYSTEM: &str = "backlight";
+
+/// The subsystem of the LED devices, as named by `SetBrightness`.
+pub const LEDS_SUBSYSTEM: &str = "leds";
+
+/// The object path of the session of the caller.
+pub const SESSION_PATH: &str = "/org/freedesktop/login1/session/auto";
+
+const LOGIND_NAME: &str = "org.freedesktop.login1";
+const SESSION_INTERFACE: &str = "org.freedesktop.login1.Session";
+const BUS_NAME: &str = "org.freedesktop.DBus";
+const BUS_PATH: &str = "/org/freedesktop/DBus";
+
+/// How long to wait for a reply before giving up.
+const TIMEOUT: Duration = Duration::from_secs(5);
+/// The largest message accepted, far above what logind sends.
+const MAX_MESSAGE_LEN: usize = 1 << 20;
+
+const METHOD_CALL: u8 = 1;
+const METHOD_RETURN: u8 = 2;
+const ERROR: u8 = 3;
+
+const FIELD_PATH: u8 = 1;
+const FIELD_INTERFACE: u8 = 2;
+const FIELD_MEMBER: u8 = 3;
+const FIELD_ERROR_NAME: u8 = 4;
+const FIELD_REPLY_SERIAL: u8 = 5;
+const FIELD_DESTINATION: u8 = 6;
+#[cfg(test)]
+const FIELD_SENDER: u8 = 7;
+const FIELD_SIGNATURE: u8 = 8;
+
+/// A connection to the logind session of the caller, opened on first use.
+///
+/// The connection is kept, and opened again if it breaks.
+pub struct Session {
+    address: String,
+    connection: Mutex<Option<Connection>>,
+}
+
+impl Session {
+    /// Creates a session reached through the bus at the D-Bus `address`,
+    /// like `unix:path=/run/dbus/system_bus_socket`.
+    pub fn new<S: Into<String>>(address: S) -> Self {
+        Self {
+            address: address.into(),
+            connection: Mutex::new(None),
+        }
+    }
+
+    /// Creates a session reached through the system bus, at the address in
+    /// [`SYSTEM_BUS_ADDRESS_VAR`] if set, [`SYSTEM_BUS_ADDRESS`] otherwise.
+    ///
+    /// [`SYSTEM_BUS_ADDRESS_VAR`]: constant.SYSTEM_BUS_ADDRESS_VAR.html
+    /// [`SYSTEM_BUS_ADDRESS`]: constant.SYSTEM_BUS_ADDRESS.html
+    pub fn system() -> Self {
+        Self::new(
+            env::var(SYSTEM_BUS_ADDRESS_VAR).unwrap_or_else(|_| SYSTEM_BUS_ADDRESS.to_string()),
+        )
+    }
+
+    /// Returns the address of the bus.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Sets the brightness of the device `name` of `subsystem`,
+    /// either [`BACKLIGHT_SUBSYSTEM`] or [`LEDS_SUBSYSTEM`], to the raw `value`.
+    ///
+    /// Failures to reach the bus are reported as I/O errors on its socket,
+    /// and the refusals of logind as [`Error::Logind`].
+    ///
+    /// [`BACKLIGHT_SUBSYSTEM`]: constant.BACKLIGHT_SUBSYSTEM.html
+    /// [`LEDS_SUBSYSTEM`]: constant.LEDS_SUBSYSTEM.html
+    /// [`Error::Logind`]: ../enum.Error.html#variant.Logind
+    pub fn set_brightness(&self, subsystem: &str, name: &str, value: u32) -> Result<()> {
+        let call = Message::method_call(
+            LOGIND_NAME,
+            SESSION_PATH,
+            SESSION_INTERFACE,
+            "SetBrightness",
+            vec![
+                Arg::Str(subsystem.to_string()),
+                Arg::Str(name.to_string()),
+                Arg::U32(value),
+            ],
+        );
+        let socket = self.socket();
+        let mut connection = self.connection.lock().unwrap();
+        if connection.is_none() {
+            *connection = Some(Connection::open(&self.address).map_err(|e| Error::io(&socket, e))?);
+        }
+        let reply = match connection.as_mut().map(|c| c.call(call)) {
+            Some(Ok(reply)) => reply,
+            Some(Err(e)) => {
+                *connection = None;
+                return Err(Error::io(&socket, e));
+            }
+            None => unreachable!(),
+        };
+        match reply.kind {
+            METHOD_RETURN => Ok(()),
+            _ => Err(Error::Logind {
+                path: socket,
+                name: reply
+                    .string_field(FIELD_ERROR_NAME)
+                    .unwrap_or("")
+                    .to_string(),
+                message: match reply.body.first() {
+                    Some(Arg::Str(message)) => message.clone(),
+                    _ => String::new(),
+                },
+            }),
+        }
+    }
+
+    /// Returns the path of the first socket of the address, for the errors.
+    fn socket(&self) -> PathBuf {
+        self.address
+            .split(';')
+            .find_map(|entry| entry.strip_prefix("unix:"))
+            .and_then(|params| {
+                params
+                    .split(',')
+                    .find_map(|param| param.split_once('='))
+                    .map(|(_, value)| unescape(value))
+            })
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(&self.address))
+    }
+}
+
+/// Returns `true` if `error` is a refusal of logind, or of the bus on its
+/// behalf, rather than a sign that logind cannot be reached at all.
+pub(crate) fn is_refusal(error: &Error) -> bool {
+    match error {
+        Error::Logind { name, .. } => {
+            name.starts_with("org.freedesktop.login1.")
+                || name == "org.freedesktop.DBus.Error.AccessDenied"
+        }
+        _ => false,
+    }
+}
+
+impl fmt::Debug for Session {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Session")
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+/// A connection to a bus, authenticated and registered.
+struct Connection {
+    stream: UnixStream,
+    serial: u32,
+}
+
+impl Connection {
+    /// Connects to the first reachable Unix socket of `address`.
+    fn open(address: &str) -> io::Result<Self> {
+        let mut last_error =
+            io::Error::new(io::ErrorKind::InvalidInput, "no supported D-Bus address");
+        for entry in address.split(';') {
+            match connect(entry) {
+                Ok(stream) => {
+                    let mut connection = Connection { stream, serial: 0 };
+                    connection.authenticate()?;
+                    connection.call(Message::method_call(
+                        BUS_NAME,
+                        BUS_PATH,
+                        BUS_NAME,
+                        "Hello",
+                        Vec::new(),
+                    ))?;
+                    return Ok(connection);
+                }
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+
+    /// Authenticates with the credentials of the socket.
+    fn authenticate(&mut self) -> io::Result<()> {
+        self.stream.set_read_timeout(Some(TIMEOUT))?;
+        let uid: String = sys::effective_uid()
+            .to_string()
+            .bytes()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        write!(self.stream, "\0AUTH EXTERNAL {}\r\n", uid)?;
+        let line = read_line(&mut self.stream)?;
+        if !line.starts_with("OK ") {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("D-Bus authentication failed: {}", line),
+            ));
+        }
+        self.stream.write_all(b"BEGIN\r\n")
+    }
+
+    /// Sends `call` and waits for its reply, skipping the other messages.
+    fn call(&mut self, mut call: Message) -> io::Result<Message> {
+        self.serial += 1;
+        call.serial = self.serial;
+        self.stream.write_all(&call.encode())?;
+        loop {
+            let message = Message::read(&mut self.stream)?;
+            let is_reply = message.kind == METHOD_RETURN || message.kind == ERROR;
+            if is_reply && message.u32_field(FIELD_REPLY_SERIAL) == Some(self.serial) {
+                return Ok(message);
+            }
+        }
+    }
+}
+
+/// Connects to a single entry of a D-Bus address.
+fn connect(entry: &str) -> io::Result<UnixStream> {
+    let params = entry.strip_prefix("unix:").ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("unsupported D-Bus address '{}'", entry),
+        )
+    })?;
+    for (key, value) in params.split(',').filter_map(|param| param.split_once('=')) {
+        match key {
+            "path" => return UnixStream::connect(unescape(value)),
+            "abstract" => {
+                use std::os::linux::net::SocketAddrExt;
+                let address = std::os::unix::net::SocketAddr::from_abstract_name(unescape(value))?;
+                return UnixStream::connect_addr(&address);
+            }
+            _ => {}
+        }
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("unsupported D-Bus address '{}'", entry),
+    ))
+}
+
+/// Decodes the `%XX` escapes of an address value.
+fn unescape(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes.get(i + 1..i + 3).and_then(|hex| {
+            std::str::from_utf8(hex)
+                .ok()
+                .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        });
+        match hex {
+            Some(byte) if bytes[i] == b'%' => {
+                decoded.push(byte);
+                i += 3;
+            }
+            _ => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Reads a line of the authentication protocol, without the final `\r\n`.
+///
+/// The bytes are read one by one, so that nothing after the line is consumed.
+fn read_line(stream: &mut UnixStream) -> io::Result<String> {
+    let mut line = Vec::new();
+    let mut byte = [0];
+    while !line.ends_with(b"\r\n") {
+        stream.read_exact(&mut byte)?;
+        line.push(byte[0]);
+        if line.len() > 1024 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "D-Bus authentication line too long",
+            ));
+        }
+    }
+    line.truncate(line.len() - 2);
+    Ok(String::from_utf8_lossy(&line).into_owned())
+}
+
+/// A value of a message, among the few types needed here.
+#[derive(Debug, Clone, PartialEq)]
+enum Arg {
+    Str(String),
+    ObjectPath(String),
+    Signature(String),
+    U32(u32),
+}
+
+impl Arg {
+    fn signature(&self) -> char {
+        match self {
+            Arg::Str(_) => 's',
+            Arg::ObjectPath(_) => 'o',
+            Arg::Signature(_) => 'g',
+            Arg::U32(_) => 'u',
+        }
+    }
+}
+
+/// A D-Bus message.
+#[derive(Debug)]
+struct Message {
+    kind: u8,
+    serial: u32,
+    fields: Vec<(u8, Arg)>,
+    body: Vec<Arg>,
+}
+
+impl Message {
+    fn method_call(
+        destination: &str,
+        path: &str,
+        interface: &str,
+        member: &str,
+        body: Vec<Arg>,
+    ) -> Self {
+        Message {
+            kind: METHOD_CALL,
+            serial: 0,
+            fields: vec![
+                (FIELD_PATH, Arg::ObjectPath(path.to_string())),
+                (FIELD_INTERFACE, Arg::Str(interface.to_string())),
+                (FIELD_MEMBER, Arg::Str(member.to_string())),
+                (FIELD_DESTINATION, Arg::Str(destination.to_string())),
+            ],
+            body,
+        }
+    }
+
+    #[cfg(test)]
+    fn reply(
+        serial: u32,
+        reply_serial: u32,
+        kind: u8,
+        mut fields: Vec<(u8, Arg)>,
+        body: Vec<Arg>,
+    ) -> Self {
+        fields.push((FIELD_REPLY_SERIAL, Arg::U32(reply_serial)));
+        Message {
+            kind,
+            serial,
+            fields,
+            body,
+        }
+    }
+
+    fn string_field(&self, code: u8) -> Option<&str> {
+        self.fields.iter().find_map(|(c, arg)| match arg {
+            Arg::Str(s) | Arg::ObjectPath(s) | Arg::Signature(s) if *c == code => Some(&s[..]),
+            _ => None,
+        })
+    }
+
+    fn u32_field(&self, code: u8) -> Option<u32> {
+        self.fields.iter().find_map(|(c, arg)| match arg {
+            Arg::U32(value) if *c == code => Some(*value),
+            _ => None,
+        })
+    }
+
+    /// Marshals the message in little-endian order.
+    fn encode(&self) -> Vec<u8> {
+        let mut body = Writer::default();
+        for arg in &self.body {
+            body.arg(arg);
+        }
+        let signature: String = self.body.iter().map(Arg::signature).collect();
+
+        let mut w = Writer::default();
+        w.buf.extend_from_slice(&[b'l', self.kind, 0, 1]);
+        w.u32(body.buf.len() as u32);
+        w.u32(self.serial);
+        w.u32(0);
+        let mut fields: Vec<&(u8, Arg)> = self.fields.iter().collect();
+        let signature_field = (FIELD_SIGNATURE, Arg::Signature(signature));
+        if !self.body.is_empty() {
+            fields.push(&signature_field);
+        }
+        for (code, arg) in fields {
+            w.align(8);
+            w.u8(*code);
+            w.signature(&arg.signature().to_string());
+            w.arg(arg);
+        }
+        let fields_len = (w.buf.len() - 16) as u32;
+        w.buf[12..16].copy_from_slice(&fields_len.to_le_bytes());
+        w.align(8);
+        w.buf.extend_from_slice(&body.buf);
+        w.buf
+    }
+
+    /// Reads and unmarshals a message.
+    ///
+    /// Only the types of [`Arg`] are decoded; the body is cut at the first other one.
+    fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut buf = vec![0; 16];
+        reader.read_exact(&mut buf)?;
+        let big_endian = match buf[0] {
+            b'l' => false,
+            b'B' => true,
+            _ => return Err(invalid_data("invalid D-Bus endianness")),
+        };
+        let mut r = Reader {
+            buf: &buf,
+            pos: 4,
+            big_endian,
+        };
+        let body_len = r.u32()? as usize;
+        let serial = r.u32()?;
+        let fields_end = 16 + r.u32()? as usize;
+        let body_start = (fields_end + 7) & !7;
+        if body_start + body_len > MAX_MESSAGE_LEN {
+            return Err(invalid_data("D-Bus message too long"));
+        }
+        buf.resize(body_start + body_len, 0);
+        reader.read_exact(&mut buf[16..])?;
+
+        let mut r = Reader {
+            buf: &buf,
+            pos: 16,
+            big_endian,
+        };
+        let mut fields = Vec::new();
+        while r.pos < fields_end {
+            r.align(8)?;
+            let code = r.u8()?;
+            let signature = r.signature()?;
+            if signature.len() != 1 {
+                return Err(invalid_data("unsupported D-Bus header field"));
+            }
+            fields.push((code, r.arg(signature.as_bytes()[0])?));
+        }
+        let mut message = Message {
+            kind: buf[1],
+            serial,
+            fields,
+            body: Vec::new(),
+        };
+        r.pos = body_start;
+        let signature = message
+            .string_field(FIELD_SIGNATURE)
+            .unwrap_or("")
+            .to_string();
+        for c in signature.bytes() {
+            match r.arg(c) {
+                Ok(arg) => message.body.push(arg),
+                Err(_) => break,
+            }
+        }
+        Ok(message)
+    }
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Marshals values, aligning them from the start of the buffer.
+#[derive(Default)]
+struct Writer {
+    buf: Vec<u8>,
+}
+
+impl Writer {
+    fn align(&mut self, n: usize) {
+        let len = (self.buf.len() + n - 1) / n * n;
+        self.buf.resize(len, 0);
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.buf.push(value);
+    }
+
+    fn u32(&mut self, value: u32) {
+        self.align(4);
+        self.buf.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn string(&mut self, value: &str) {
+        self.u32(value.len() as u32);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    fn signature(&mut self, value: &str) {
+        self.u8(value.len() as u8);
+        self.buf.extend_from_slice(value.as_bytes());
+        self.buf.push(0);
+    }
+
+    fn arg(&mut self, arg: &Arg) {
+        match arg {
+            Arg::Str(s) | Arg::ObjectPath(s) => self.string(s),
+            Arg::Signature(s) => self.signature(s),
+            Arg::U32(value) => self.u32(*value),
+        }
+    }
+}
+
+/// Unmarshals values, aligning them from the start of the buffer.
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    big_endian: bool,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> io::Result<&[u8]> {
+        let bytes = self
+            .buf
+            .get(self.pos..self.pos + len)
+            .ok_or_else(|| invalid_data("truncated D-Bus message"))?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn align(&mut self, n: usize) -> io::Result<()> {
+        let padding = (n - self.pos % n) % n;
+        self.take(padding).map(|_| ())
+    }
+
+    fn u8(&mut self) -> io::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> io::Result<u32> {
+        self.align(4)?;
+        let big_endian = self.big_endian;
+        let bytes = self.take(4)?;
+        let bytes = [bytes[0], bytes[1], bytes[2], bytes[3]];
+        Ok(if big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    fn text(&mut self, len: usize) -> io::Result<String> {
+        let bytes = self.take(len + 1)?;
+        String::from_utf8(bytes[..len].to_vec()).map_err(|_| invalid_data("invalid D-Bus string"))
+    }
+
+    fn string(&mut self) -> io::Result<String> {
+        let len = self.u32()? as usize;
+        self.text(len)
+    }
+
+    fn signature(&mut self) -> io::Result<String> {
+        let len = usize::from(self.u8()?);
+        self.text(len)
+    }
+
+    fn arg(&mut self, signature: u8) -> io::Result<Arg> {
+        match signature {
+            b's' => self.string().map(Arg::Str),
+            b'o' => self.string().map(Arg::ObjectPath),
+            b'g' => self.signature().map(Arg::Signature),
+            b'u' => self.u32().map(Arg::U32),
+            _ => Err(invalid_data("unsupported D-Bus type")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{DeviceBuilder, Led, BRIGHTNESS};
+    use std::fs;
+    use std::io::{BufRead, BufReader};
+    use std::path::Path;
+    use std::process::{Child, Command, Stdio};
+    use std::thread;
+
+    /// A private bus run by `dbus-daemon`, stopped when dropped.
+    struct PrivateBus {
+        daemon: Child,
+        address: String,
+    }
+
+    impl PrivateBus {
+        /// Starts the bus, or returns `None` if `dbus-daemon` is missing.
+        fn start() -> Option<Self> {
+            let mut daemon = Command::new("dbus-daemon")
+                .args(["--session", "--print-address", "--nofork"])
+                .stdout(Stdio::piped())
+                .stderr(Stdio::null())
+                .spawn()
+                .ok()?;
+            let mut address = String::new();
+            let stdout = daemon.stdout.take().expect("the output is piped");
+            BufReader::new(stdout).read_line(&mut address).unwrap();
+            Some(Self {
+                daemon,
+                address: address.trim().to_string(),
+            })
+        }
+    }
+
+    impl Drop for PrivateBus {
+        fn drop(&mut self) {
+            let _ = self.daemon.kill();
+            let _ = self.daemon.wait();
+        }
+    }
+
+    /// Takes the name of logind on the bus at `address` and answers
+    /// `SetBrightness` in a thread, writing to the devices of `dir`.
+    fn stand_in_for_logind(address: &str, dir: &Path) {
+        let mut connection = Connection::open(address).unwrap();
+        let request = Message::method_call(
+            BUS_NAME,
+            BUS_PATH,
+            BUS_NAME,
+            "RequestName",
+            vec![Arg::Str(LOGIND_NAME.to_string()), Arg::U32(0)],
+        );
+        // 1 is DBUS_REQUEST_NAME_REPLY_PRIMARY_OWNER.
+        assert_eq!(connection.call(request).unwrap().body, [Arg::U32(1)]);
+        connection.stream.set_read_timeout(None).unwrap();
+        let dir = dir.to_path_buf();
+        thread::spawn(move || {
+            while let Ok(call) = Message::read(&mut connection.stream) {
+                if call.kind != METHOD_CALL {
+                    continue;
+                }
+                let sender = call.string_field(FIELD_SENDER).unwrap().to_string();
+                let written = match (call.string_field(FIELD_MEMBER), &call.body[..]) {
+                    (Some("SetBrightness"), [Arg::Str(_), Arg::Str(name), Arg::U32(value)]) => {
+                        fs::write(dir.join(name).join(BRIGHTNESS), value.to_string()).is_ok()
+                    }
+                    _ => false,
+                };
+                connection.serial += 1;
+                let destination = (FIELD_DESTINATION, Arg::Str(sender));
+                let reply = if written {
+                    let fields = vec![destination];
+                    Message::reply(
+                        connection.serial,
+                        call.serial,
+                        METHOD_RETURN,
+                        fields,
+                        vec![],
+                    )
+                } else {
+                    let name = Arg::Str("org.freedesktop.login1.NoSuchDevice".to_string());
+                    let fields = vec![destination, (FIELD_ERROR_NAME, name)];
+                    let body = vec![Arg::Str("No such device".to_string())];
+                    Message::reply(connection.serial, call.serial, ERROR, fields, body)
+                };
+                if connection.stream.write_all(&reply.encode()).is_err() {
+                    return;
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn set_brightness_on_a_private_bus() {
+        let bus = match PrivateBus::start() {
+            Some(bus) => bus,
+            None => return eprintln!("dbus-daemon not found, skipping"),
+        };
+        let root = std::env::temp_dir().join("rust-lcd-test-logind");
+        let _ = fs::remove_dir_all(&root);
+        for (name, max) in &[("intel_backlight", "100"), ("dell::kbd_backlight", "3")] {
+            fs::create_dir_all(root.join(name)).unwrap();
+            fs::write(root.join(name).join("max_brightness"), max).unwrap();
+        }
+
+        // Without logind, the bus itself answers, which is no refusal.
+        let session = Session::new(bus.address.clone());
+        let error = session.set_brightness(LEDS_SUBSYSTEM, "dell::kbd_backlight", 1);
+        match error {
+            Err(ref e @ Error::Logind { ref name, .. }) => {
+                assert_eq!(name, "org.freedesktop.DBus.Error.ServiceUnknown");
+                assert!(!is_refusal(e));
+            }
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        stand_in_for_logind(&bus.address, &root);
+        let dev = DeviceBuilder::new()
+            .logind(Some(Session::new(bus.address.clone())))
+            .via_logind(true)
+            .build(root.join("intel_backlight"));
+        dev.set_brightness(40).unwrap();
+        assert_eq!(dev.brightness().unwrap(), 40);
+
+        let led = Led::new(root.join("dell::kbd_backlight"))
+            .logind(Some(session))
+            .via_logind(true);
+        led.set_brightness(2).unwrap();
+        assert_eq!(led.brightness().unwrap(), 2);
+
+        let session = Session::new(bus.address.clone());
+        match session.set_brightness(LEDS_SUBSYSTEM, "input3::capslock", 0) {
+            Err(ref e @ Error::Logind { .. }) => assert!(is_refusal(e)),
+            other => panic!("unexpected result {:?}", other),
+        }
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
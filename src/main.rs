@@ -2,7 +2,57 @@
 //!
 //! # Usage
 //!
-//! Just run `rust-lcd` at your terminal (with superuser permissions).
+//! Run `rust-lcd [OPTIONS] [COMMAND]` at your terminal (with superuser permissions),
+//! where `COMMAND` is one of:
+//!
+//! - `on`: turn the devices ON;
+//! - `off`: turn the devices OFF;
+//! - `toggle`: toggle the devices (the default);
+//! - `set STATE`: set the devices to `STATE`, one of `unblank`, `normal`,
+//!   `vsync-suspend`, `hsync-suspend`, `powerdown` or a level from `0` to `4`,
+//!   where `on` and `off` stand for `unblank` and `normal` like the commands;
+//! - `status` (or `list`): print the type, power state and brightness of the
+//!   devices, in the format chosen with `--format`: `plain`, `tsv` or `json`;
+//! - `brightness [LEVEL]`: print the brightness of the devices or set it to
+//!   `LEVEL`, either a raw value or a percentage like `40%`, or change it
+//!   by a signed step like `+5%`, `-5%` or `-10`, or by the configured step
+//!   with `up` and `down`;
+//! - `save`: save the brightness and power state of the devices;
+//! - `restore`: restore the saved brightness and power state of the devices;
+//! - `watch`: print the changes of the brightness and power state of the devices
+//!   until interrupted, one line (or one JSON object) per change;
+//! - `monitor`: print the devices appearing and disappearing until interrupted;
+//! - `daemon`: serve the devices on a Unix socket until interrupted;
+//! - `trigger [TRIGGER]`: print the triggers of the keyboard backlights or
+//!   activate `TRIGGER`;
+//! - `scene [NAME]`: print the scenes of the configuration or apply the scene
+//!   `NAME`, setting the power state and brightness of the devices it names,
+//!   the keyboard backlights included when their folder exists.
+//!
+//! With `--keyboard`, the keyboard backlights of `/sys/class/leds` are
+//! selected along with the panels, and with `--ddc`, the external monitors
+//! reachable over DDC/CI through `/dev/i2c-*` as well.
+//!
+//! When a daemon is running, the commands changing the devices are sent to it,
+//! so that only the daemon needs superuser permissions. Otherwise, when writing
+//! the brightness is not permitted, it is set through systemd-logind, which
+//! allows it to the owner of the session; `--logind` always goes this way.
+//! When setuid, logind is only reached with `--logind`.
+//!
+//! The defaults of the options, and the settings of single devices, are read
+//! from `/etc/rust-lcd.toml`, then from `$XDG_CONFIG_HOME/rust-lcd/config.toml`
+//! (see [`Config`](../rust_lcd/struct.Config.html) for the format); the
+//! command-line options take precedence over both. The devices pinned in the
+//! configuration are the only ones touched when no device is selected,
+//! except by the scenes, which touch the devices they name.
+//!
+//! When installed setuid, `rust-lcd` clears its environment, only accepts
+//! devices whose files resolve inside `/sys/devices`, and drops its
+//! privileges as soon as these files are open. The daemon cannot run this way.
+//!
+//! By default every device is selected; run `rust-lcd --help` for the options
+//! restricting the selection. The options may also follow the command, as in
+//! `rust-lcd scene night --fade 500`, until a `--`.
 //!
 //! # Examples
 //!
@@ -12,18 +62,1021 @@
 //! ```
 //! or as a simple user:
 //! ```bash
-//! user@host$ sudo rust-lcd
+//! user@host$ sudo rust-lcd --device intel_backlight off
 //! ```
 
-use rust_lcd::{iterate_devices, BACKLIGHT_PATH};
-use std::io;
-// use std::path::{Path, PathBuf};
+use rust_lcd::daemon::{Client, Server, SOCKET_PATH};
+use rust_lcd::ddc::{DdcIter, DdcMonitor, I2C_DEV_PATH};
+use rust_lcd::logind::Session;
+use rust_lcd::{
+    catch_interrupts, default_state_path, drop_privileges, is_setuid, preferred_device,
+    sanitize_environment, Backlight, ChangeEvent, Config, Device, DeviceBuilder, DeviceMonitor,
+    HotplugEvent, Led, LedIter, Level, PowerState, Settings, State, Step, TryDeviceIter, Watcher,
+    BACKLIGHT_PATH, DEFAULT_FLOOR, LEDS_PATH, SYSFS_DEVICES_PATH,
+};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// The step of `brightness up` and `brightness down` when none is configured.
+const DEFAULT_STEP: Step = Step::Percent(5.0);
+
+const USAGE: &str = "\
+usage: rust-lcd [OPTIONS] [on|off|toggle|set STATE|status|list|brightness [LEVEL|up|down]|save|restore|watch|monitor|daemon|trigger [TRIGGER]|scene [NAME]]
+
+Options:
+  -d, --device PATTERN  select the devices whose name matches PATTERN,
+                        which may contain the wildcards '*' and '?'
+  -i, --index N         select the N-th device, counting from 0 in name order
+  -p, --preferred       select the preferred device, by type
+                        (raw, then platform, then firmware)
+  -a, --all             select all the devices (the default)
+  -r, --root DIR        look for devices in DIR instead of /sys/class/backlight
+  -k, --keyboard        also select the keyboard backlights, after the panels
+      --led-root DIR    look for keyboard backlights in DIR instead of
+                        /sys/class/leds
+      --ddc             also select the external monitors supporting DDC/CI,
+                        after the keyboard backlights
+      --i2c-root DIR    look for monitors in DIR instead of /dev
+  -f, --fade MS         fade the brightness over MS milliseconds, overriding
+                        the fade of a scene
+  -c, --curve CURVE     the curve of the fade: linear (the default),
+                        ease-in-out or exponential
+  -s, --scale SCALE     the scale of percentages: linear (the default),
+                        log (perceptual CIE lightness) or gamma=EXPONENT
+  -e, --exponent E      shorthand for '--scale gamma=E'
+  -m, --min LEVEL       never set the brightness below LEVEL, either a raw
+                        value or a percentage like '5%' (1 by default)
+      --max LEVEL       never set the brightness above LEVEL (the maximum
+                        brightness by default)
+      --step LEVEL      the step of 'brightness up' and 'brightness down'
+                        (5% by default)
+      --force           ignore the minimum and maximum brightness, allowing 0
+      --config FILE     read the configuration from FILE instead of
+                        /etc/rust-lcd.toml and $XDG_CONFIG_HOME/rust-lcd/config.toml
+      --no-config       ignore the configuration files
+      --format FORMAT   the output format of 'status' and 'watch':
+                        plain (the default), tsv or json
+      --interval MS     read the attributes every MS milliseconds while
+                        watching (200 by default), or scan for devices every
+                        MS milliseconds while monitoring (2000 by default)
+      --socket PATH     the socket of the daemon, /run/rust-lcd.sock by default
+      --admin-group GID let the members of the group GID force the brightness
+                        through the daemon, which only root may do otherwise
+      --no-daemon       change the devices directly even if a daemon is running
+      --logind          set the brightness through systemd-logind instead of
+                        writing it, which is only tried when denied otherwise
+  -S, --state FILE      save and restore the state in FILE instead of
+                        /var/lib/rust-lcd/state (as root) or
+                        $XDG_STATE_HOME/rust-lcd/state
+  -h, --help            print this help and exit
+  -V, --version         print the version and exit";
+
+enum Command {
+    On,
+    Off,
+    Toggle,
+    Set(PowerState),
+    Status,
+    Brightness(Option<Target>),
+    Save,
+    Restore,
+    Watch,
+    Monitor,
+    Daemon,
+    Trigger(Option<String>),
+    Scene(Option<String>),
+}
+
+#[derive(Clone, Copy)]
+enum Format {
+    Plain,
+    Tsv,
+    Json,
+}
+
+enum Target {
+    Level(Level),
+    Step(Step),
+    Up,
+    Down,
+}
+
+impl Target {
+    fn parse(s: &str) -> Option<Self> {
+        if s == "up" {
+            Some(Target::Up)
+        } else if s == "down" {
+            Some(Target::Down)
+        } else if s.starts_with('+') || s.starts_with('-') {
+            s.parse().ok().map(Target::Step)
+        } else {
+            s.parse().ok().map(Target::Level)
+        }
+    }
+}
+
+enum Selector {
+    Pattern(String),
+    Index(usize),
+    Preferred,
+}
+
+struct Options {
+    root: PathBuf,
+    keyboard: bool,
+    led_root: PathBuf,
+    ddc: bool,
+    i2c_root: PathBuf,
+    all: bool,
+    selectors: Vec<Selector>,
+    /// The settings given as flags, overriding the configuration.
+    overrides: Settings,
+    config: Option<PathBuf>,
+    use_config: bool,
+    state: Option<PathBuf>,
+    format: Format,
+    interval: Option<Duration>,
+    socket: PathBuf,
+    admin_group: Option<u32>,
+    use_daemon: bool,
+    logind: bool,
+    force: bool,
+    /// Whether the process runs setuid, and must be hardened.
+    privileged: bool,
+    command: Command,
+}
+
+fn parse_args() -> Options {
+    let mut options = Options {
+        root: PathBuf::from(BACKLIGHT_PATH),
+        keyboard: false,
+        led_root: PathBuf::from(LEDS_PATH),
+        ddc: false,
+        i2c_root: PathBuf::from(I2C_DEV_PATH),
+        all: false,
+        selectors: Vec::new(),
+        overrides: Settings::default(),
+        config: None,
+        use_config: true,
+        state: None,
+        format: Format::Plain,
+        interval: None,
+        socket: PathBuf::from(SOCKET_PATH),
+        admin_group: None,
+        use_daemon: true,
+        logind: false,
+        force: false,
+        privileged: is_setuid(),
+        command: Command::Toggle,
+    };
+    let mut positionals = Vec::new();
+    let mut args = env::args().skip(1);
+
+    // The options may come anywhere, so they are parsed before the command
+    // and its arguments are bound.
+    while let Some(arg) = args.next() {
+        if !is_option(&arg) {
+            positionals.push(arg);
+            continue;
+        }
+        if arg == "--" {
+            positionals.extend(&mut args);
+            break;
+        }
+        // Accept both `--opt value` and `--opt=value`.
+        let (flag, inline) = match arg.find('=') {
+            Some(i) if arg.starts_with("--") => {
+                (arg[..i].to_string(), Some(arg[i + 1..].to_string()))
+            }
+            _ => (arg.clone(), None),
+        };
+        let mut value = |name: &str| {
+            inline
+                .clone()
+                .or_else(|| args.next())
+                .unwrap_or_else(|| usage(&format!("missing value for '{}'", name)))
+        };
+        match flag.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                process::exit(0);
+            }
+            "-V" | "--version" => {
+                println!("rust-lcd {}", env!("CARGO_PKG_VERSION"));
+                process::exit(0);
+            }
+            "-a" | "--all" => options.all = true,
+            "-p" | "--preferred" => options.selectors.push(Selector::Preferred),
+            "-r" | "--root" => options.root = PathBuf::from(value(&flag)),
+            "-k" | "--keyboard" => options.keyboard = true,
+            "--led-root" => options.led_root = PathBuf::from(value(&flag)),
+            "--ddc" => options.ddc = true,
+            "--i2c-root" => options.i2c_root = PathBuf::from(value(&flag)),
+            "-d" | "--device" => options.selectors.push(Selector::Pattern(value(&flag))),
+            "-i" | "--index" => {
+                let index = value(&flag);
+                match index.parse() {
+                    Ok(index) => options.selectors.push(Selector::Index(index)),
+                    Err(_) => usage(&format!("invalid index '{}'", index)),
+                }
+            }
+            "-f" | "--fade" => {
+                let ms = value(&flag);
+                match ms.parse() {
+                    Ok(ms) => options.overrides.fade = Some(Duration::from_millis(ms)),
+                    Err(_) => usage(&format!("invalid fade duration '{}'", ms)),
+                }
+            }
+            "-c" | "--curve" => match value(&flag).parse() {
+                Ok(curve) => options.overrides.curve = Some(curve),
+                Err(e) => usage(&e.to_string()),
+            },
+            "-s" | "--scale" => match value(&flag).parse() {
+                Ok(scale) => options.overrides.scale = Some(scale),
+                Err(e) => usage(&e.to_string()),
+            },
+            "-e" | "--exponent" => match format!("gamma={}", value(&flag)).parse() {
+                Ok(scale) => options.overrides.scale = Some(scale),
+                Err(_) => usage("the exponent must be a positive number"),
+            },
+            "-m" | "--min" => match value(&flag).parse() {
+                Ok(floor) => options.overrides.min = Some(floor),
+                Err(e) => usage(&e.to_string()),
+            },
+            "--max" => match value(&flag).parse() {
+                Ok(ceiling) => options.overrides.max = Some(ceiling),
+                Err(e) => usage(&e.to_string()),
+            },
+            "--step" => match value(&flag).parse() {
+                Ok(Level::Raw(raw)) => options.overrides.step = Some(Step::Raw(raw)),
+                Ok(Level::Percent(percent)) => {
+                    options.overrides.step = Some(Step::Percent(percent))
+                }
+                Err(e) => usage(&e.to_string()),
+            },
+            "--force" => options.force = true,
+            "--config" => options.config = Some(PathBuf::from(value(&flag))),
+            "--no-config" => options.use_config = false,
+            "--socket" => options.socket = PathBuf::from(value(&flag)),
+            "--admin-group" => {
+                let gid = value(&flag);
+                match gid.parse() {
+                    Ok(gid) => options.admin_group = Some(gid),
+                    Err(_) => usage(&format!("invalid group ID '{}'", gid)),
+                }
+            }
+            "--no-daemon" => options.use_daemon = false,
+            "--logind" => options.logind = true,
+            "-S" | "--state" => options.state = Some(PathBuf::from(value(&flag))),
+            "--interval" => {
+                let ms = value(&flag);
+                match ms.parse() {
+                    Ok(ms) => options.interval = Some(Duration::from_millis(ms)),
+                    Err(_) => usage(&format!("invalid interval '{}'", ms)),
+                }
+            }
+            "--format" => {
+                options.format = match value(&flag).as_str() {
+                    "plain" => Format::Plain,
+                    "tsv" => Format::Tsv,
+                    "json" => Format::Json,
+                    other => usage(&format!("unknown format '{}'", other)),
+                }
+            }
+            _ => usage(&format!("unknown option '{}'", arg)),
+        }
+    }
+
+    let mut positionals = positionals.into_iter();
+    let command = positionals.next().map(|name| match name.as_str() {
+        "toggle" => Command::Toggle,
+        "on" => Command::On,
+        "off" => Command::Off,
+        "status" | "list" => Command::Status,
+        "save" => Command::Save,
+        "restore" => Command::Restore,
+        "watch" => Command::Watch,
+        "monitor" => Command::Monitor,
+        "daemon" => Command::Daemon,
+        "trigger" => {
+            options.keyboard = true;
+            Command::Trigger(positionals.next())
+        }
+        "scene" => {
+            let name = positionals.next();
+            // Scenes commonly dim the keyboard along with the panel, when there
+            // is one; a folder given to a setuid binary is checked, not probed.
+            let leds = if options.privileged && options.led_root != Path::new(LEDS_PATH) {
+                true
+            } else {
+                options.led_root.is_dir()
+            };
+            options.keyboard |= name.is_some() && leds;
+            Command::Scene(name)
+        }
+        "set" => match positionals.next() {
+            Some(state) => match state.parse() {
+                Ok(state) => Command::Set(state),
+                Err(e) => usage(&e.to_string()),
+            },
+            None => usage("missing state for 'set'"),
+        },
+        "brightness" => Command::Brightness(positionals.next().map(|level| {
+            Target::parse(&level)
+                .unwrap_or_else(|| usage(&format!("invalid brightness '{}'", level)))
+        })),
+        _ => usage(&format!("unknown command '{}'", name)),
+    });
+    if let Some(arg) = positionals.next() {
+        usage(&format!("unexpected argument '{}'", arg));
+    }
 
-fn main() -> io::Result<()> {
-    for device in iterate_devices(BACKLIGHT_PATH)? {
-        println!("{:?}", device);
-        device.toggle()?;
+    if options.all && !options.selectors.is_empty() {
+        usage("'--all' cannot be combined with other selections");
+    }
+    if options.force {
+        options.overrides.min = Some(Level::Raw(0));
+        options.overrides.max = Some(Level::Percent(100.0));
     }
+    if let Some(command) = command {
+        options.command = command;
+    }
+    options
+}
+
+/// Returns `true` if `arg` is an option, rather than a command or an
+/// argument like the negative step `-5%`.
+fn is_option(arg: &str) -> bool {
+    let mut chars = arg.chars();
+    chars.next() == Some('-') && chars.next().is_some_and(|c| !c.is_ascii_digit())
+}
 
+fn usage(message: &str) -> ! {
+    eprintln!("rust-lcd: {}", message);
+    eprintln!("{}", USAGE);
+    process::exit(2);
+}
+
+/// Matches `name` against a shell-like `pattern` supporting `*` and `?`.
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Position of the last `*` in the pattern and the name position it is matched against.
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((sp, sn)) = star {
+            p = sp + 1;
+            n = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// A device found by [`select_devices`].
+enum Found {
+    Panel(Device),
+    Keyboard(Led),
+    Monitor(DdcMonitor),
+}
+
+impl Found {
+    fn name(&self) -> &str {
+        match self {
+            Found::Panel(device) => device.name(),
+            Found::Keyboard(led) => led.name(),
+            Found::Monitor(monitor) => monitor.name(),
+        }
+    }
+
+    /// Opens the files of a sysfs device inside `root`; monitors are already open.
+    fn open_within(self, root: &str) -> Result<Found, rust_lcd::Error> {
+        Ok(match self {
+            Found::Panel(device) => Found::Panel(device.open_within(root)?),
+            Found::Keyboard(led) => Found::Keyboard(led.open_within(root)?),
+            Found::Monitor(monitor) => Found::Monitor(monitor),
+        })
+    }
+
+    fn backlight(&self) -> &dyn Backlight {
+        match self {
+            Found::Panel(device) => device,
+            Found::Keyboard(led) => led,
+            Found::Monitor(monitor) => monitor,
+        }
+    }
+
+    fn into_backlight(self) -> Box<dyn Backlight> {
+        match self {
+            Found::Panel(device) => Box::new(device),
+            Found::Keyboard(led) => Box::new(led),
+            Found::Monitor(monitor) => Box::new(monitor),
+        }
+    }
+}
+
+/// Returns the logind session to fall back on, which a setuid binary only
+/// reaches when asked to.
+fn logind_session(options: &Options) -> Option<Session> {
+    if options.logind || !options.privileged {
+        Some(Session::system())
+    } else {
+        None
+    }
+}
+
+/// Lists the panels in name order, then the keyboard backlights and the
+/// monitors if requested, and applies the selectors.
+fn select_devices(options: &Options) -> Result<Vec<Found>, Box<dyn Error>> {
+    let builder = DeviceBuilder::new()
+        .logind(logind_session(options))
+        .via_logind(options.logind);
+    let mut panels =
+        TryDeviceIter::with_builder(&options.root, builder)?.collect::<Result<Vec<_>, _>>()?;
+    panels.sort_by(|a, b| a.name().cmp(b.name()));
+    let preferred =
+        preferred_device(panels.iter().cloned()).map(|device| device.path().to_path_buf());
+    let mut devices: Vec<Found> = panels.into_iter().map(Found::Panel).collect();
+    if options.keyboard {
+        let mut leds = LedIter::new(&options.led_root)?
+            .filter(|led| led.as_ref().map_or(true, Led::is_keyboard))
+            .collect::<Result<Vec<_>, _>>()?;
+        leds.sort_by(|a, b| a.name().cmp(b.name()));
+        devices.extend(leds.into_iter().map(|led| {
+            let led = led.logind(logind_session(options));
+            Found::Keyboard(led.via_logind(options.logind))
+        }));
+    }
+    if options.ddc {
+        devices.extend(DdcIter::new(&options.i2c_root)?.map(Found::Monitor));
+    }
+    if options.selectors.is_empty() {
+        return Ok(devices);
+    }
+
+    let mut selected = vec![false; devices.len()];
+    for selector in &options.selectors {
+        let mut found = false;
+        for (i, device) in devices.iter().enumerate() {
+            let matches = match selector {
+                Selector::Pattern(pattern) => glob_match(pattern, device.name()),
+                Selector::Index(index) => i == *index,
+                Selector::Preferred => match device {
+                    Found::Panel(device) => preferred.as_deref() == Some(device.path()),
+                    Found::Keyboard(_) | Found::Monitor(_) => false,
+                },
+            };
+            if matches {
+                selected[i] = true;
+                found = true;
+            }
+        }
+        if !found {
+            let what = match selector {
+                Selector::Pattern(pattern) => format!("matching '{}'", pattern),
+                Selector::Index(index) => format!("with index {}", index),
+                Selector::Preferred => "at all".to_string(),
+            };
+            return Err(format!("no device {} in {}", what, options.root.display()).into());
+        }
+    }
+
+    Ok(devices
+        .into_iter()
+        .zip(selected)
+        .filter(|(_, selected)| *selected)
+        .map(|(device, _)| device)
+        .collect())
+}
+
+/// Prints the attributes of `devices`; the unreadable ones are left empty.
+fn print_status(devices: &[Box<dyn Backlight>], format: Format) {
+    fn field<T: ToString>(value: Option<T>) -> Option<String> {
+        value.map(|value| value.to_string())
+    }
+
+    // The keys of the fields, and whether their values are numbers.
+    const KEYS: [(&str, bool); 7] = [
+        ("name", false),
+        ("path", false),
+        ("type", false),
+        ("power", false),
+        ("brightness", true),
+        ("max_brightness", true),
+        ("actual_brightness", true),
+    ];
+
+    if let Format::Tsv = format {
+        let keys: Vec<&str> = KEYS.iter().map(|(key, _)| *key).collect();
+        println!("{}", keys.join("\t"));
+    }
+    let mut rows = Vec::new();
+    for snapshot in devices.iter().map(|device| device.snapshot()) {
+        let row = [
+            Some(snapshot.name().to_string()),
+            snapshot.path().map(|path| path.display().to_string()),
+            field(snapshot.backlight_type()),
+            field(snapshot.power()),
+            field(snapshot.brightness()),
+            field(snapshot.max_brightness()),
+            field(snapshot.actual_brightness()),
+        ];
+        match format {
+            Format::Plain => println!(
+                "{} ({}): {}, brightness {}/{} (actual {})",
+                snapshot.name(),
+                row[2].as_deref().unwrap_or("unknown"),
+                row[3].as_deref().unwrap_or("unknown"),
+                row[4].as_deref().unwrap_or("?"),
+                row[5].as_deref().unwrap_or("?"),
+                row[6].as_deref().unwrap_or("?"),
+            ),
+            Format::Tsv => {
+                let row: Vec<&str> = row.iter().map(|v| v.as_deref().unwrap_or("")).collect();
+                println!("{}", row.join("\t"));
+            }
+            Format::Json => {
+                let fields: Vec<String> = KEYS
+                    .iter()
+                    .zip(&row)
+                    .map(|((key, numeric), value)| {
+                        let value = match value {
+                            None => "null".to_string(),
+                            Some(value) if *numeric => value.clone(),
+                            Some(value) => json_string(value),
+                        };
+                        format!("{}:{}", json_string(key), value)
+                    })
+                    .collect();
+                rows.push(format!("{{{}}}", fields.join(",")));
+            }
+        }
+    }
+    if let Format::Json = format {
+        println!("[{}]", rows.join(","));
+    }
+}
+
+fn print_event(event: &ChangeEvent, format: Format) {
+    fn value(value: Option<i32>, none: &str) -> String {
+        value.map_or_else(|| none.to_string(), |value| value.to_string())
+    }
+
+    let timestamp = event
+        .timestamp
+        .duration_since(UNIX_EPOCH)
+        .map(|t| t.as_secs_f64())
+        .unwrap_or(0.0);
+    match format {
+        Format::Plain => println!(
+            "{:.3} {} {}: {} -> {}",
+            timestamp,
+            event.device,
+            event.attribute,
+            value(event.old, "?"),
+            value(event.new, "?")
+        ),
+        Format::Tsv => println!(
+            "{:.3}\t{}\t{}\t{}\t{}",
+            timestamp,
+            event.device,
+            event.attribute,
+            value(event.old, ""),
+            value(event.new, "")
+        ),
+        Format::Json => println!(
+            "{{\"timestamp\":{:.3},\"device\":{},\"attribute\":{},\"old\":{},\"new\":{}}}",
+            timestamp,
+            json_string(&event.device),
+            json_string(event.attribute.name()),
+            value(event.old, "null"),
+            value(event.new, "null")
+        ),
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Connects to the daemon, if the command changes the devices and one is running.
+///
+/// The daemon serves the devices of `/sys/class/backlight`, so it is not used
+/// with another `--root`, nor with `--ddc` or `--logind`. The devices are
+/// listed first, so that the daemon also knows those that appeared since.
+fn connect_daemon(options: &Options) -> Option<Client> {
+    let changes = match options.command {
+        Command::On | Command::Off | Command::Toggle | Command::Set(_) => true,
+        Command::Brightness(ref target) => target.is_some(),
+        _ => false,
+    };
+    let default_roots = options.root == Path::new(BACKLIGHT_PATH)
+        && (!options.keyboard || options.led_root == Path::new(LEDS_PATH));
+    if !changes || !options.use_daemon || !default_roots || options.ddc || options.logind {
+        return None;
+    }
+    let mut client = Client::connect(&options.socket).ok()?;
+    client.list().ok()?;
+    Some(client)
+}
+
+/// Applies the command to `device` through the daemon.
+fn run_remote(
+    client: &mut Client,
+    device: &dyn Backlight,
+    settings: &Settings,
+    options: &Options,
+) -> Result<(), Box<dyn Error>> {
+    let name = device.name();
+    match options.command {
+        Command::On => client.power_on(name)?,
+        Command::Off => client.power_off(name)?,
+        Command::Toggle => {
+            client.toggle(name)?;
+        }
+        Command::Set(state) => client.set_power_state(name, state)?,
+        Command::Brightness(Some(ref level)) => {
+            // The settings are those of the client, so the target is computed
+            // here and the daemon only checks the range of the device.
+            let target = brightness_target(device, level, settings)?;
+            // The limits of the client may be wider than those of the daemon.
+            let force = options.force || must_force(device, level, target)?;
+            match settings.fade {
+                Some(duration) => {
+                    let curve = settings.curve.unwrap_or_default();
+                    if force {
+                        client.force_fade_to(name, target, duration, curve)?;
+                    } else {
+                        client.fade_to(name, target, duration, curve)?;
+                    }
+                }
+                None if force => client.force_brightness(name, target)?,
+                None => client.set_brightness(name, target)?,
+            }
+        }
+        _ => unreachable!(),
+    }
+    Ok(())
+}
+
+/// Checks `target` against the limits of `device`, telling whether it has to
+/// be forced.
+///
+/// A step only leaves the limits if the brightness already is out of them, so
+/// it is forced; any other target out of the limits is an error.
+fn must_force(device: &dyn Backlight, level: &Target, target: i32) -> Result<bool, Box<dyn Error>> {
+    let min = device.min_brightness()?;
+    let max = device.ceiling_brightness()?;
+    if (min..=max).contains(&target) {
+        return Ok(false);
+    }
+    if !matches!(level, Target::Level(_)) {
+        return Ok(true);
+    }
+    let name = device.name();
+    Err(if target < min {
+        format!(
+            "brightness {} of {} is below the minimum {}",
+            target, name, min
+        )
+    } else {
+        format!(
+            "brightness {} of {} is above the maximum {}",
+            target, name, max
+        )
+    }
+    .into())
+}
+
+fn brightness_target(
+    device: &dyn Backlight,
+    target: &Target,
+    settings: &Settings,
+) -> Result<i32, Box<dyn Error>> {
+    let step = settings.step.unwrap_or(DEFAULT_STEP);
+    Ok(match *target {
+        Target::Level(Level::Raw(value)) => value,
+        Target::Level(Level::Percent(percent)) => device
+            .percent_to_raw(percent)?
+            .min(device.ceiling_brightness()?)
+            .max(device.min_brightness()?),
+        Target::Step(step) => device.step_target(step)?,
+        Target::Up => device.step_target(step)?,
+        Target::Down => device.step_target(-step)?,
+    })
+}
+
+/// Loads the configuration chosen by the options.
+fn load_config(options: &Options) -> Result<Config, Box<dyn Error>> {
+    Ok(match options.config {
+        _ if !options.use_config => Config::default(),
+        Some(ref path) => Config::load(path)?,
+        None => Config::load_default()?,
+    })
+}
+
+/// Checks the folders listed with the privileges: the device folders must
+/// lie in the classes or the devices of sysfs, and the i2c nodes in /dev.
+fn check_roots(options: &Options) -> Result<(), Box<dyn Error>> {
+    let mut roots = vec![&options.root];
+    if options.keyboard {
+        roots.push(&options.led_root);
+    }
+    for root in roots {
+        let canonical = fs::canonicalize(root)?;
+        if !canonical.starts_with("/sys/class") && !canonical.starts_with(SYSFS_DEVICES_PATH) {
+            return Err(format!(
+                "refusing to look for devices in {} when setuid",
+                root.display()
+            )
+            .into());
+        }
+    }
+    if options.ddc && fs::canonicalize(&options.i2c_root)? != Path::new("/dev") {
+        return Err(format!(
+            "refusing to look for monitors in {} when setuid",
+            options.i2c_root.display()
+        )
+        .into());
+    }
     Ok(())
 }
+
+/// Opens the files of `devices` inside sysfs, then drops the privileges.
+fn harden(devices: Vec<Found>) -> Result<Vec<Found>, Box<dyn Error>> {
+    let devices = devices
+        .into_iter()
+        .map(|device| device.open_within(SYSFS_DEVICES_PATH))
+        .collect::<Result<Vec<_>, _>>()?;
+    drop_privileges()?;
+    Ok(devices)
+}
+
+/// Prints the triggers of the selected keyboard backlights, or activates `trigger`.
+fn run_trigger(devices: &[Found], trigger: Option<&str>) -> Result<(), Box<dyn Error>> {
+    let leds: Vec<&Led> = devices
+        .iter()
+        .filter_map(|device| match device {
+            Found::Keyboard(led) => Some(led),
+            Found::Panel(_) | Found::Monitor(_) => None,
+        })
+        .collect();
+    if leds.is_empty() {
+        return Err("no keyboard backlight selected".into());
+    }
+    for led in leds {
+        match trigger {
+            Some(trigger) => led.set_trigger(trigger)?,
+            None => {
+                let active = led.trigger()?.unwrap_or_else(|| "none".to_string());
+                let triggers: Vec<String> = led
+                    .triggers()?
+                    .into_iter()
+                    .map(|name| {
+                        if name == active {
+                            format!("[{}]", name)
+                        } else {
+                            name
+                        }
+                    })
+                    .collect();
+                println!("{}: {}", led.name(), triggers.join(" "));
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run(options: &Options) -> Result<(), Box<dyn Error>> {
+    if let Command::Daemon = options.command {
+        if options.privileged {
+            return Err("the daemon cannot run setuid, start it as root instead".into());
+        }
+        // The daemon serves any device, so only the defaults apply.
+        let mut settings = load_config(options)?.defaults;
+        settings.merge(&options.overrides);
+        let mut builder = DeviceBuilder::new()
+            .scale(settings.scale.unwrap_or_default())
+            .floor(settings.min.unwrap_or(DEFAULT_FLOOR));
+        if let Some(max) = settings.max {
+            builder = builder.ceiling(max);
+        }
+        let mut server = Server::bind(&options.socket, &options.root, builder)?;
+        if let Some(gid) = options.admin_group {
+            server = server.admin_group(gid);
+        }
+        if options.keyboard {
+            server = server.with_leds(&options.led_root)?;
+        }
+        let interrupt = catch_interrupts();
+        return Ok(server.serve_until(interrupt.flag())?);
+    }
+    if let Command::Monitor = options.command {
+        if options.privileged {
+            drop_privileges()?;
+        }
+        let builder = DeviceBuilder::new();
+        let interrupt = catch_interrupts();
+        let mut monitor =
+            DeviceMonitor::with_builder(&options.root, builder)?.until(interrupt.flag());
+        if let Some(interval) = options.interval {
+            monitor = monitor.interval(interval);
+        }
+        for event in monitor {
+            match event? {
+                HotplugEvent::Added(device) => println!("added {}", device.path().display()),
+                HotplugEvent::Removed(path) => println!("removed {}", path.display()),
+            }
+        }
+        return Ok(());
+    }
+
+    if let Command::Scene(None) = options.command {
+        if options.privileged {
+            drop_privileges()?;
+        }
+        for scene in &load_config(options)?.scenes {
+            let targets: Vec<String> = scene
+                .devices
+                .iter()
+                .map(|(key, target)| format!("{}={}", key, target))
+                .collect();
+            println!("{}: {}", scene.name, targets.join(", "));
+        }
+        return Ok(());
+    }
+
+    if options.privileged {
+        check_roots(options)?;
+    }
+    let mut devices = select_devices(options)?;
+    if options.privileged {
+        devices = harden(devices)?;
+    }
+    // Read only now, since the file of the user must not be read with the privileges.
+    let config = load_config(options)?;
+    let scene = matches!(options.command, Command::Scene(_));
+    if options.selectors.is_empty() && !options.all && !scene {
+        devices.retain(|device| config.is_pinned(device.backlight()));
+    }
+    if let Command::Trigger(ref trigger) = options.command {
+        return run_trigger(&devices, trigger.as_deref());
+    }
+    let (devices, settings): (Vec<Box<dyn Backlight>>, Vec<Settings>) = devices
+        .into_iter()
+        .map(|device| {
+            let mut settings = config.settings(device.backlight());
+            settings.merge(&options.overrides);
+            let mut device = device.into_backlight();
+            settings.apply(&mut *device)?;
+            Ok((device, settings))
+        })
+        .collect::<Result<Vec<_>, rust_lcd::Error>>()?
+        .into_iter()
+        .unzip();
+
+    // Resolved only now, since it depends on the user once the privileges are dropped.
+    let state_path = options.state.clone().unwrap_or_else(default_state_path);
+    match options.command {
+        Command::Save => {
+            let mut state = State::load_or_default(&state_path)?;
+            state.capture(&devices);
+            return Ok(state.save(&state_path)?);
+        }
+        Command::Restore => {
+            State::load(&state_path)?.restore(&devices)?;
+            return Ok(());
+        }
+        Command::Status => {
+            print_status(&devices, options.format);
+            return Ok(());
+        }
+        Command::Scene(Some(ref name)) => {
+            let mut scene = config
+                .scene(name)
+                .ok_or_else(|| format!("unknown scene '{}'", name))?
+                .clone();
+            scene.fade = options.overrides.fade.or(scene.fade);
+            scene.curve = options.overrides.curve.or(scene.curve);
+            let interrupt = catch_interrupts();
+            if scene.apply_until(&devices, interrupt.flag())? == 0 {
+                if !options.keyboard {
+                    return Err(format!(
+                        "scene '{}' matches no device, and {} does not exist",
+                        name,
+                        options.led_root.display()
+                    )
+                    .into());
+                }
+                return Err(format!("scene '{}' matches no device", name).into());
+            }
+            return Ok(());
+        }
+        Command::Watch => {
+            let interrupt = catch_interrupts();
+            let mut watcher = Watcher::new(devices)?.until(interrupt.flag());
+            if let Some(interval) = options.interval {
+                watcher = watcher.interval(interval);
+            }
+            for event in watcher {
+                print_event(&event?, options.format);
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    if let Some(mut client) = connect_daemon(options) {
+        for (device, settings) in devices.iter().zip(&settings) {
+            run_remote(&mut client, device, settings, options)?;
+        }
+        return Ok(());
+    }
+
+    // Only the fades stop cleanly, any other command ends on the first signal.
+    let fading = matches!(options.command, Command::Brightness(Some(_)))
+        && settings.iter().any(|settings| settings.fade.is_some());
+    let interrupt = fading.then(catch_interrupts);
+    for (device, settings) in devices.iter().zip(&settings) {
+        match options.command {
+            Command::On => device.power_on()?,
+            Command::Off => device.power_off()?,
+            Command::Toggle => {
+                device.toggle()?;
+            }
+            Command::Set(state) => device.set_power_state(state)?,
+            Command::Brightness(None) => println!(
+                "{}: {}/{} ({:.0}%)",
+                device.name(),
+                device.brightness()?,
+                device.max_brightness()?,
+                device.brightness_percent()?
+            ),
+            Command::Brightness(Some(ref level)) => {
+                let target = brightness_target(device, level, settings)?;
+                let force = must_force(device, level, target)?;
+                match settings.fade {
+                    Some(duration) => {
+                        let curve = settings.curve.unwrap_or_default();
+                        let stop = interrupt.as_ref().expect("the fades catch the signals");
+                        if force {
+                            device.force_fade_to_until(target, duration, curve, stop.flag())?;
+                        } else {
+                            device.fade_to_until(target, duration, curve, stop.flag())?;
+                        }
+                    }
+                    None if force => device.force_brightness(target)?,
+                    None => device.set_brightness(target)?,
+                }
+            }
+            Command::Save
+            | Command::Restore
+            | Command::Status
+            | Command::Watch
+            | Command::Monitor
+            | Command::Daemon
+            | Command::Trigger(_)
+            | Command::Scene(_) => unreachable!(),
+        }
+    }
+
+    Ok(())
+}
+
+fn main() {
+    if is_setuid() {
+        sanitize_environment();
+    }
+    let options = parse_args();
+
+    if let Err(e) = run(&options) {
+        eprintln!("rust-lcd: {}", e);
+        process::exit(1);
+    }
+}
@@ -0,0 +1,87 @@
+//! An in-memory backlight, for tests and examples.
+
+use crate::{Backlight, Error, PowerState, Result, BRIGHTNESS};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// A backlight keeping its power state and brightness in memory.
+///
+/// Clones share the same state, so a test can keep one to inspect what
+/// the code under test did with another.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{Backlight, MockBacklight, PowerState, Step};
+/// let mock = MockBacklight::new("panel", 200).with_brightness(100);
+/// let device: Box<dyn Backlight> = Box::new(mock.clone());
+/// assert_eq!(device.step(Step::Percent(10.0)).unwrap(), 120);
+/// assert!(device.set_brightness(201).is_err());
+/// device.power_off().unwrap();
+/// assert_eq!(mock.brightness().unwrap(), 120);
+/// assert_eq!(mock.power_state().unwrap(), PowerState::Normal);
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockBacklight {
+    name: String,
+    max_brightness: i32,
+    state: Arc<Mutex<(PowerState, i32)>>,
+}
+
+impl MockBacklight {
+    /// Creates a backlight named `name`, powered on at its maximum brightness.
+    pub fn new<S: Into<String>>(name: S, max_brightness: i32) -> Self {
+        Self {
+            name: name.into(),
+            max_brightness,
+            state: Arc::new(Mutex::new((PowerState::Unblank, max_brightness))),
+        }
+    }
+
+    /// Sets the initial brightness.
+    pub fn with_brightness(self, brightness: i32) -> Self {
+        self.state.lock().unwrap().1 = brightness;
+        self
+    }
+
+    /// Sets the initial power state.
+    pub fn with_power_state(self, state: PowerState) -> Self {
+        self.state.lock().unwrap().0 = state;
+        self
+    }
+}
+
+impl Backlight for MockBacklight {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn power_state(&self) -> Result<PowerState> {
+        Ok(self.state.lock().unwrap().0)
+    }
+
+    fn set_power_state(&self, state: PowerState) -> Result<()> {
+        self.state.lock().unwrap().0 = state;
+        Ok(())
+    }
+
+    fn brightness(&self) -> Result<i32> {
+        Ok(self.state.lock().unwrap().1)
+    }
+
+    fn max_brightness(&self) -> Result<i32> {
+        Ok(self.max_brightness)
+    }
+
+    fn set_brightness(&self, value: i32) -> Result<()> {
+        if value < 0 || value > self.max_brightness {
+            return Err(Error::invalid_value(
+                Path::new(&self.name).join(BRIGHTNESS),
+                value,
+                format!("0..={}", self.max_brightness),
+            ));
+        }
+        self.state.lock().unwrap().1 = value;
+        Ok(())
+    }
+}
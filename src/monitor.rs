@@ -0,0 +1,240 @@
+//! Detection of devices appearing and disappearing.
+
+use crate::{sys, Device, DeviceBuilder, Error, Result, TryDeviceIter};
+use std::collections::{BTreeMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The default interval between two scans of the folder of a [`DeviceMonitor`].
+///
+/// [`DeviceMonitor`]: struct.DeviceMonitor.html
+pub const MONITOR_INTERVAL: Duration = Duration::from_secs(2);
+
+/// A device appearing or disappearing.
+#[derive(Debug, Clone)]
+pub enum HotplugEvent {
+    /// A new device appeared.
+    Added(Device),
+    /// The device at this path disappeared.
+    Removed(PathBuf),
+}
+
+/// How a [`DeviceMonitor`] is woken up when devices change.
+///
+/// Whatever the backend, the folder is scanned again at every interval,
+/// so that no change is missed.
+///
+/// [`DeviceMonitor`]: struct.DeviceMonitor.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MonitorBackend {
+    /// The kernel uevents of the `backlight` subsystem, read from a netlink socket.
+    Uevent,
+    /// Inotify on the folder, which works for ordinary folders but not
+    /// for the ones in sysfs.
+    Inotify,
+    /// Periodic scans of the folder alone.
+    Polling,
+}
+
+enum Wakeup {
+    Uevent(sys::UeventSocket),
+    Inotify(sys::Inotify),
+    Polling,
+}
+
+/// A blocking iterator over the devices appearing in and disappearing from a folder.
+///
+/// The devices that are present when the monitor is created are not reported;
+/// they can be obtained with [`devices`].
+///
+/// [`devices`]: #method.devices
+///
+/// # Examples
+///
+/// ```no_run
+/// # use rust_lcd::{DeviceMonitor, HotplugEvent, BACKLIGHT_PATH};
+/// for event in DeviceMonitor::new(BACKLIGHT_PATH).unwrap() {
+///     match event.unwrap() {
+///         HotplugEvent::Added(device) => println!("added {}", device.name()),
+///         HotplugEvent::Removed(path) => println!("removed {}", path.display()),
+///     }
+/// }
+/// ```
+pub struct DeviceMonitor<'a> {
+    dir: PathBuf,
+    builder: DeviceBuilder,
+    known: BTreeMap<PathBuf, Device>,
+    wakeup: Wakeup,
+    interval: Duration,
+    stop: Option<&'a AtomicBool>,
+    pending: VecDeque<HotplugEvent>,
+}
+
+impl<'a> DeviceMonitor<'a> {
+    /// Creates a monitor of the devices in `dir`.
+    ///
+    /// The kernel uevents are used when available, then inotify, then polling.
+    pub fn new<P: AsRef<Path>>(dir: P) -> Result<Self> {
+        Self::with_builder(dir, DeviceBuilder::new())
+    }
+
+    /// Creates a monitor of the devices in `dir`, built from the `builder` template.
+    pub fn with_builder<P: AsRef<Path>>(dir: P, builder: DeviceBuilder) -> Result<Self> {
+        let dir = dir.as_ref();
+        let wakeup = match sys::UeventSocket::new() {
+            // Uevents only concern sysfs, so they are useless for other folders.
+            Ok(socket) if dir.starts_with("/sys") => Wakeup::Uevent(socket),
+            _ => match sys::Inotify::new() {
+                Ok(inotify) => {
+                    inotify.add_dir_watch(dir).map_err(|e| Error::io(dir, e))?;
+                    Wakeup::Inotify(inotify)
+                }
+                Err(_) => Wakeup::Polling,
+            },
+        };
+        Self::with_wakeup(dir, builder, wakeup)
+    }
+
+    /// Creates a monitor of the devices in `dir` that only polls.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_lcd::{DeviceBuilder, DeviceMonitor, HotplugEvent};
+    /// # use std::fs;
+    /// use std::time::Duration;
+    /// let root = std::env::temp_dir().join("rust-lcd-doc-monitor");
+    /// # let _ = fs::remove_dir_all(&root);
+    /// fs::create_dir_all(&root).unwrap();
+    /// let mut monitor = DeviceMonitor::polling(&root, DeviceBuilder::new())
+    ///     .unwrap()
+    ///     .interval(Duration::from_millis(10));
+    /// fs::create_dir(root.join("dp_aux_backlight")).unwrap();
+    /// fs::write(root.join("dp_aux_backlight").join("bl_power"), "0\n").unwrap();
+    /// match monitor.next().unwrap().unwrap() {
+    ///     HotplugEvent::Added(device) => assert_eq!(device.name(), "dp_aux_backlight"),
+    ///     HotplugEvent::Removed(path) => panic!("unexpected removal of {:?}", path),
+    /// }
+    /// # fs::remove_dir_all(&root).unwrap();
+    /// ```
+    pub fn polling<P: AsRef<Path>>(dir: P, builder: DeviceBuilder) -> Result<Self> {
+        Self::with_wakeup(dir.as_ref(), builder, Wakeup::Polling)
+    }
+
+    fn with_wakeup(dir: &Path, builder: DeviceBuilder, wakeup: Wakeup) -> Result<Self> {
+        let mut monitor = Self {
+            dir: dir.to_path_buf(),
+            builder,
+            known: BTreeMap::new(),
+            wakeup,
+            interval: MONITOR_INTERVAL,
+            stop: None,
+            pending: VecDeque::new(),
+        };
+        monitor.known = monitor.scan_dir()?;
+        Ok(monitor)
+    }
+
+    /// Sets the interval between two scans of the folder,
+    /// [`MONITOR_INTERVAL`] by default.
+    ///
+    /// [`MONITOR_INTERVAL`]: constant.MONITOR_INTERVAL.html
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Makes the iterator end as soon as `stop` becomes `true`.
+    ///
+    /// The flag is checked at least once per interval.
+    pub fn until(mut self, stop: &'a AtomicBool) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Returns the backend used to wake up the monitor.
+    pub fn backend(&self) -> MonitorBackend {
+        match self.wakeup {
+            Wakeup::Uevent(_) => MonitorBackend::Uevent,
+            Wakeup::Inotify(_) => MonitorBackend::Inotify,
+            Wakeup::Polling => MonitorBackend::Polling,
+        }
+    }
+
+    /// Returns the devices currently known to the monitor.
+    pub fn devices(&self) -> impl Iterator<Item = &Device> {
+        self.known.values()
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.is_some_and(|stop| stop.load(Ordering::SeqCst))
+    }
+
+    /// Lists the devices in the folder; a missing folder has no devices.
+    fn scan_dir(&self) -> Result<BTreeMap<PathBuf, Device>> {
+        let devices = match TryDeviceIter::with_builder(&self.dir, self.builder.clone()) {
+            Ok(devices) => devices,
+            Err(Error::NotFound { .. }) => return Ok(BTreeMap::new()),
+            Err(e) => return Err(e),
+        };
+        devices
+            .map(|device| device.map(|device| (device.path().to_path_buf(), device)))
+            .collect()
+    }
+
+    /// Waits until something may have changed, or the interval expires.
+    fn wait(&mut self) -> Result<()> {
+        let result = match self.wakeup {
+            Wakeup::Uevent(ref mut socket) => socket.wait(self.interval, "backlight").map(|_| ()),
+            Wakeup::Inotify(ref mut inotify) => inotify.wait(self.interval).map(|_| ()),
+            Wakeup::Polling => {
+                let deadline = Instant::now() + self.interval;
+                // Sleep in short slices, so that the stop flag is noticed quickly.
+                while !self.stopped() {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        break;
+                    }
+                    thread::sleep((deadline - now).min(Duration::from_millis(50)));
+                }
+                Ok(())
+            }
+        };
+        result.map_err(|e| Error::io(&self.dir, e))
+    }
+}
+
+impl Iterator for DeviceMonitor<'_> {
+    type Item = Result<HotplugEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.stopped() {
+                return None;
+            }
+            if let Err(e) = self.wait() {
+                return Some(Err(e));
+            }
+            let current = match self.scan_dir() {
+                Ok(current) => current,
+                Err(e) => return Some(Err(e)),
+            };
+            for path in self.known.keys() {
+                if !current.contains_key(path) {
+                    self.pending.push_back(HotplugEvent::Removed(path.clone()));
+                }
+            }
+            for (path, device) in &current {
+                if !self.known.contains_key(path) {
+                    self.pending.push_back(HotplugEvent::Added(device.clone()));
+                }
+            }
+            self.known = current;
+        }
+    }
+}
@@ -0,0 +1,155 @@
+//! Power states of backlight devices.
+
+use std::convert::TryFrom;
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The power state of a backlight device, as read from [`BL_POWER`].
+///
+/// The kernel uses the `FB_BLANK_*` levels from `<linux/fb.h>`:
+/// only [`Unblank`] means that the backlight is ON, all the other levels
+/// blank the panel with increasing depth.
+///
+/// The state can be converted to and from the kernel integer with
+/// [`i32::from`] and [`PowerState::try_from`], and to and from its name
+/// with [`Display`] and [`FromStr`].
+///
+/// [`BL_POWER`]: constant.BL_POWER.html
+/// [`Unblank`]: #variant.Unblank
+/// [`i32::from`]: https://doc.rust-lang.org/stable/std/convert/trait.From.html
+/// [`PowerState::try_from`]: https://doc.rust-lang.org/stable/std/convert/trait.TryFrom.html
+/// [`Display`]: https://doc.rust-lang.org/stable/std/fmt/trait.Display.html
+/// [`FromStr`]: https://doc.rust-lang.org/stable/std/str/trait.FromStr.html
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::PowerState;
+/// use std::convert::TryFrom;
+/// assert_eq!(PowerState::try_from(4), Ok(PowerState::Powerdown));
+/// assert_eq!(i32::from(PowerState::VSyncSuspend), 2);
+/// assert_eq!("off".parse(), Ok(PowerState::Normal));
+/// assert_eq!(PowerState::HSyncSuspend.to_string(), "hsync-suspend");
+/// assert!(!PowerState::Normal.is_on());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "kebab-case"))]
+pub enum PowerState {
+    /// The backlight is ON (`FB_BLANK_UNBLANK`, `0`).
+    Unblank,
+    /// The panel is blanked, but the backlight may stay ON (`FB_BLANK_NORMAL`, `1`).
+    Normal,
+    /// The vertical sync is suspended (`FB_BLANK_VSYNC_SUSPEND`, `2`).
+    #[cfg_attr(feature = "serde", serde(rename = "vsync-suspend"))]
+    VSyncSuspend,
+    /// The horizontal sync is suspended (`FB_BLANK_HSYNC_SUSPEND`, `3`).
+    #[cfg_attr(feature = "serde", serde(rename = "hsync-suspend"))]
+    HSyncSuspend,
+    /// The backlight is powered down (`FB_BLANK_POWERDOWN`, `4`).
+    Powerdown,
+}
+
+impl PowerState {
+    /// All the power states, ordered by kernel value.
+    pub const ALL: [PowerState; 5] = [
+        PowerState::Unblank,
+        PowerState::Normal,
+        PowerState::VSyncSuspend,
+        PowerState::HSyncSuspend,
+        PowerState::Powerdown,
+    ];
+
+    /// Returns `true` if the backlight is ON.
+    pub fn is_on(self) -> bool {
+        self == PowerState::Unblank
+    }
+
+    /// Returns the name of the state, as accepted by [`FromStr`].
+    ///
+    /// [`FromStr`]: https://doc.rust-lang.org/stable/std/str/trait.FromStr.html
+    pub fn name(self) -> &'static str {
+        match self {
+            PowerState::Unblank => "unblank",
+            PowerState::Normal => "normal",
+            PowerState::VSyncSuspend => "vsync-suspend",
+            PowerState::HSyncSuspend => "hsync-suspend",
+            PowerState::Powerdown => "powerdown",
+        }
+    }
+}
+
+impl From<PowerState> for i32 {
+    fn from(state: PowerState) -> i32 {
+        match state {
+            PowerState::Unblank => 0,
+            PowerState::Normal => 1,
+            PowerState::VSyncSuspend => 2,
+            PowerState::HSyncSuspend => 3,
+            PowerState::Powerdown => 4,
+        }
+    }
+}
+
+impl TryFrom<i32> for PowerState {
+    type Error = ParsePowerStateError;
+
+    fn try_from(value: i32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(PowerState::Unblank),
+            1 => Ok(PowerState::Normal),
+            2 => Ok(PowerState::VSyncSuspend),
+            3 => Ok(PowerState::HSyncSuspend),
+            4 => Ok(PowerState::Powerdown),
+            _ => Err(ParsePowerStateError(value.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for PowerState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for PowerState {
+    type Err = ParsePowerStateError;
+
+    /// Parses a state from its name, from an alias (`on`, `off`, `blank`)
+    /// or from its kernel value.
+    ///
+    /// `off` is [`Normal`], the state written by [`Backlight::power_off`];
+    /// use `powerdown` for the deepest level.
+    ///
+    /// [`Normal`]: #variant.Normal
+    /// [`Backlight::power_off`]: trait.Backlight.html#method.power_off
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "unblank" | "on" => Ok(PowerState::Unblank),
+            "normal" | "blank" | "off" => Ok(PowerState::Normal),
+            "vsync-suspend" | "vsync_suspend" => Ok(PowerState::VSyncSuspend),
+            "hsync-suspend" | "hsync_suspend" => Ok(PowerState::HSyncSuspend),
+            "powerdown" => Ok(PowerState::Powerdown),
+            other => other
+                .parse::<i32>()
+                .ok()
+                .and_then(|value| PowerState::try_from(value).ok())
+                .ok_or_else(|| ParsePowerStateError(s.to_string())),
+        }
+    }
+}
+
+/// The error returned when a [`PowerState`] cannot be parsed or converted.
+///
+/// [`PowerState`]: enum.PowerState.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePowerStateError(String);
+
+impl fmt::Display for ParsePowerStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid power state '{}'", self.0)
+    }
+}
+
+impl error::Error for ParsePowerStateError {}
@@ -0,0 +1,128 @@
+//! Mappings between brightness percentages and raw values.
+
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The scale used to convert brightness percentages to raw values.
+///
+/// A [`Linear`] scale feels very uneven, because the eye is much more
+/// sensitive to changes at low luminance: going from 10% to 20% is a huge
+/// jump while going from 80% to 100% barely changes anything.
+/// The perceptual scales compensate for this, so that equal steps of the
+/// percentage look like equal steps of lightness.
+///
+/// [`Linear`]: #variant.Linear
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::Scale;
+/// assert_eq!(Scale::Linear.to_fraction(0.5), 0.5);
+/// assert_eq!(Scale::Gamma(2.0).to_fraction(0.5), 0.25);
+/// assert!(Scale::Cie.to_fraction(0.5) < 0.2);
+/// let fraction = Scale::Cie.to_fraction(0.3);
+/// assert!((Scale::Cie.from_fraction(fraction) - 0.3).abs() < 1e-9);
+/// assert_eq!("log".parse(), Ok(Scale::Cie));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Scale {
+    /// The percentage is proportional to the raw value.
+    #[default]
+    Linear,
+    /// The raw value is proportional to the percentage raised to the exponent.
+    ///
+    /// An exponent between `2.0` and `3.0` looks roughly uniform.
+    Gamma(f64),
+    /// The percentage is the CIE 1976 lightness `L*`, and the raw value
+    /// is proportional to the corresponding relative luminance `Y`.
+    Cie,
+}
+
+impl Scale {
+    /// Maps a fraction of the percentage scale, in `0.0..=1.0`,
+    /// to a fraction of the maximum raw brightness.
+    pub fn to_fraction(self, percent: f64) -> f64 {
+        let p = percent.clamp(0.0, 1.0);
+        match self {
+            Scale::Linear => p,
+            Scale::Gamma(exponent) => p.powf(exponent),
+            Scale::Cie => {
+                let lightness = p * 100.0;
+                if lightness > 8.0 {
+                    ((lightness + 16.0) / 116.0).powi(3)
+                } else {
+                    lightness / CIE_KAPPA
+                }
+            }
+        }
+    }
+
+    /// Maps a fraction of the maximum raw brightness, in `0.0..=1.0`,
+    /// back to a fraction of the percentage scale.
+    ///
+    /// This is the inverse of [`to_fraction`](#method.to_fraction).
+    pub fn from_fraction(self, fraction: f64) -> f64 {
+        let y = fraction.clamp(0.0, 1.0);
+        match self {
+            Scale::Linear => y,
+            Scale::Gamma(exponent) => y.powf(exponent.recip()),
+            Scale::Cie => {
+                let lightness = if y > 8.0 / CIE_KAPPA {
+                    116.0 * y.cbrt() - 16.0
+                } else {
+                    y * CIE_KAPPA
+                };
+                lightness / 100.0
+            }
+        }
+    }
+}
+
+/// The slope of the linear segment of the CIE lightness near black.
+const CIE_KAPPA: f64 = 903.3;
+
+impl fmt::Display for Scale {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Scale::Linear => f.write_str("linear"),
+            Scale::Gamma(exponent) => write!(f, "gamma={}", exponent),
+            Scale::Cie => f.write_str("cie"),
+        }
+    }
+}
+
+impl FromStr for Scale {
+    type Err = ParseScaleError;
+
+    /// Parses `linear`, `cie` (or its alias `log`) and `gamma=EXPONENT`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.trim().to_ascii_lowercase();
+        match lower.as_str() {
+            "linear" => Ok(Scale::Linear),
+            "cie" | "log" => Ok(Scale::Cie),
+            other => other
+                .strip_prefix("gamma=")
+                .and_then(|exponent| exponent.parse::<f64>().ok())
+                .filter(|exponent| exponent.is_finite() && *exponent > 0.0)
+                .map(Scale::Gamma)
+                .ok_or_else(|| ParseScaleError(s.to_string())),
+        }
+    }
+}
+
+/// The error returned when a [`Scale`] cannot be parsed.
+///
+/// [`Scale`]: enum.Scale.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseScaleError(String);
+
+impl fmt::Display for ParseScaleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid scale '{}'", self.0)
+    }
+}
+
+impl error::Error for ParseScaleError {}
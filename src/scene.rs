@@ -0,0 +1,267 @@
+//! Named presets of the power and brightness of several devices.
+
+use crate::config::matches;
+use crate::fade::{self, Curve};
+use crate::{level_to_raw, Backlight, Level, PowerState, Result};
+use std::error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+/// The power state and brightness that a [`Scene`] gives to a device.
+///
+/// It is parsed from a brightness level, a power state, or both separated
+/// by a space; a level alone also turns the device on.
+///
+/// [`Scene`]: struct.Scene.html
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{Level, PowerState, SceneTarget};
+/// let target: SceneTarget = "40%".parse().unwrap();
+/// assert_eq!(target.power, Some(PowerState::Unblank));
+/// assert_eq!(target.brightness, Some(Level::Percent(40.0)));
+/// let target: SceneTarget = "off".parse().unwrap();
+/// assert_eq!(target.power, Some(PowerState::Normal));
+/// assert_eq!(target.brightness, None);
+/// assert_eq!("10 off".parse::<SceneTarget>().unwrap().to_string(), "10 normal");
+/// assert!("loud".parse::<SceneTarget>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SceneTarget {
+    /// The power state, left unchanged if `None`.
+    pub power: Option<PowerState>,
+    /// The brightness, left unchanged if `None`.
+    pub brightness: Option<Level>,
+}
+
+impl SceneTarget {
+    /// Overrides the target with the parts set in `other`.
+    pub fn merge(&mut self, other: &SceneTarget) {
+        self.power = other.power.or(self.power);
+        self.brightness = other.brightness.or(self.brightness);
+    }
+}
+
+impl fmt::Display for SceneTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.brightness, self.power) {
+            (Some(brightness), Some(PowerState::Unblank)) => write!(f, "{}", brightness),
+            (Some(brightness), Some(power)) => write!(f, "{} {}", brightness, power),
+            (Some(brightness), None) => write!(f, "{} -", brightness),
+            (None, Some(power)) => write!(f, "{}", power),
+            (None, None) => f.write_str("-"),
+        }
+    }
+}
+
+impl FromStr for SceneTarget {
+    type Err = ParseSceneTargetError;
+
+    /// Parses a level, a power state, or a level followed by a power state.
+    ///
+    /// A `-` in place of the power state leaves it unchanged.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let err = || ParseSceneTargetError(s.to_string());
+        let words: Vec<&str> = s.split_whitespace().collect();
+        let (brightness, power) = match words[..] {
+            ["-"] => (None, None),
+            [word] => match word.parse::<Level>() {
+                Ok(level) => (Some(level), Some(PowerState::Unblank)),
+                Err(_) => (None, Some(word.parse().map_err(|_| err())?)),
+            },
+            [level, "-"] => (Some(level.parse().map_err(|_| err())?), None),
+            [level, power] => (
+                Some(level.parse().map_err(|_| err())?),
+                Some(power.parse().map_err(|_| err())?),
+            ),
+            _ => return Err(err()),
+        };
+        Ok(SceneTarget { power, brightness })
+    }
+}
+
+/// The error returned when a [`SceneTarget`] cannot be parsed.
+///
+/// [`SceneTarget`]: struct.SceneTarget.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseSceneTargetError(String);
+
+impl fmt::Display for ParseSceneTargetError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid scene target '{}'", self.0)
+    }
+}
+
+impl error::Error for ParseSceneTargetError {}
+
+/// A named preset of the power state and brightness of a set of devices.
+///
+/// The devices are designated by their name or their stable path, as in
+/// the sections of a [`Config`], where scenes are read from. Applying a
+/// scene turns the devices on before changing their brightness, and off
+/// after it, so that a fade is visible; with a fade duration, every device
+/// fades at the same pace. The devices that were off start at their target
+/// brightness instead of fading to it.
+///
+/// [`Config`]: struct.Config.html
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{Backlight, MockBacklight, PowerState, Scene};
+/// # use std::time::Duration;
+/// let panel = MockBacklight::new("intel_backlight", 100);
+/// let keyboard = MockBacklight::new("dell::kbd_backlight", 2);
+/// let devices: Vec<Box<dyn Backlight>> = vec![
+///     Box::new(panel.clone()),
+///     Box::new(keyboard.clone()),
+///     Box::new(MockBacklight::new("acpi_video0", 10)),
+/// ];
+///
+/// let scene = Scene::new("movie")
+///     .device("intel_backlight", "20%".parse().unwrap())
+///     .device("dell::kbd_backlight", "off".parse().unwrap())
+///     .fade(Duration::from_millis(50));
+/// assert_eq!(scene.apply(&devices).unwrap(), 2);
+/// assert_eq!(panel.brightness().unwrap(), 20);
+/// assert_eq!(keyboard.power_state().unwrap(), PowerState::Normal);
+/// assert_eq!(devices[2].brightness().unwrap(), 10);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scene {
+    /// The name of the scene.
+    pub name: String,
+    /// The targets of the devices, keyed by name or stable path.
+    pub devices: Vec<(String, SceneTarget)>,
+    /// The duration of the fade, or `None` to change the brightness at once.
+    pub fade: Option<Duration>,
+    /// The curve of the fade.
+    pub curve: Option<Curve>,
+}
+
+impl Scene {
+    /// Creates an empty scene named `name`.
+    pub fn new<S: Into<String>>(name: S) -> Self {
+        Scene {
+            name: name.into(),
+            devices: Vec::new(),
+            fade: None,
+            curve: None,
+        }
+    }
+
+    /// Adds the target of the devices designated by `key`.
+    pub fn device<S: Into<String>>(mut self, key: S, target: SceneTarget) -> Self {
+        self.devices.push((key.into(), target));
+        self
+    }
+
+    /// Sets the duration of the fade.
+    pub fn fade(mut self, duration: Duration) -> Self {
+        self.fade = Some(duration);
+        self
+    }
+
+    /// Sets the curve of the fade.
+    pub fn curve(mut self, curve: Curve) -> Self {
+        self.curve = Some(curve);
+        self
+    }
+
+    /// Returns the target of `device`, merged from the entries matching it
+    /// in order, or `None` if the scene does not touch it.
+    pub fn target<B: Backlight + ?Sized>(&self, device: &B) -> Option<SceneTarget> {
+        self.devices
+            .iter()
+            .filter(|(key, _)| matches(key, device))
+            .fold(None, |target: Option<SceneTarget>, (_, entry)| {
+                let mut target = target.unwrap_or_default();
+                target.merge(entry);
+                Some(target)
+            })
+    }
+
+    /// Applies the scene to `devices`, see [`apply_until`].
+    ///
+    /// [`apply_until`]: #method.apply_until
+    pub fn apply<'a, I, B>(&self, devices: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = &'a B>,
+        B: Backlight + ?Sized + 'a,
+    {
+        self.apply_until(devices, &AtomicBool::new(false))
+    }
+
+    /// Applies the scene to `devices`, stopping the fade when `stop` is raised.
+    ///
+    /// The brightness is kept within the floor and the ceiling of each device.
+    /// The devices the scene does not mention are left untouched; the return
+    /// value is the number of devices it was applied to. When the fade is
+    /// stopped, the devices keep the brightness they reached and the devices
+    /// to turn off stay on.
+    pub fn apply_until<'a, I, B>(&self, devices: I, stop: &AtomicBool) -> Result<usize>
+    where
+        I: IntoIterator<Item = &'a B>,
+        B: Backlight + ?Sized + 'a,
+    {
+        let mut applied = 0;
+        let mut fades = Vec::new();
+        let mut powers = Vec::new();
+        for device in devices {
+            let target = match self.target(device) {
+                Some(target) => target,
+                None => continue,
+            };
+            let brightness = match target.brightness {
+                Some(level) => {
+                    let value = level_to_raw(device, level)?;
+                    let max = device.ceiling_brightness()?;
+                    let min = device.min_brightness()?;
+                    Some(value.min(max).max(min))
+                }
+                None => None,
+            };
+            match target.power {
+                Some(power) if power.is_on() => {
+                    // Powering on a LED sets its maximum, which must not flash before the fade.
+                    if let Some(brightness) = brightness {
+                        if !device.power_state()?.is_on() {
+                            device.set_brightness(brightness)?;
+                        }
+                    }
+                    device.set_power_state(power)?
+                }
+                Some(power) => powers.push((device, power)),
+                None => {}
+            }
+            if let Some(brightness) = brightness {
+                fades.push((device, device.brightness()?, brightness));
+            }
+            applied += 1;
+        }
+
+        match self.fade {
+            Some(duration) if !duration.is_zero() => {
+                let curve = self.curve.unwrap_or_default();
+                fade::fade_all_until(&fades, duration, curve, stop)?;
+                if stop.load(Ordering::SeqCst) {
+                    return Ok(applied);
+                }
+            }
+            _ => {
+                for &(device, _, brightness) in &fades {
+                    device.set_brightness(brightness)?;
+                }
+            }
+        }
+        for (device, power) in powers {
+            device.set_power_state(power)?;
+        }
+        Ok(applied)
+    }
+}
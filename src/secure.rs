@@ -0,0 +1,196 @@
+//! Hardening of the privileged, setuid mode.
+//!
+//! A setuid binary runs with the privileges of its owner on behalf of any
+//! user, who controls its arguments and its environment. The functions here
+//! let it resolve the devices while privileged, keep the attribute files open
+//! and then give the privileges up before doing anything else.
+
+use crate::{sys, Device, Error, Result, ACTUAL_BRIGHTNESS, TYPE};
+use std::collections::BTreeMap;
+use std::env;
+use std::ffi::OsString;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// The directory holding the devices in sysfs.
+///
+/// The entries of [`BACKLIGHT_PATH`] are symbolic links into it.
+///
+/// [`BACKLIGHT_PATH`]: constant.BACKLIGHT_PATH.html
+pub const SYSFS_DEVICES_PATH: &str = "/sys/devices";
+
+/// The environment variables kept by [`sanitize_environment`].
+///
+/// They are only consulted after the privileges are dropped.
+///
+/// [`sanitize_environment`]: fn.sanitize_environment.html
+const KEPT_VARIABLES: [&str; 3] = ["HOME", "XDG_STATE_HOME", "XDG_CONFIG_HOME"];
+
+/// The attribute files of a device opened by [`Device::open_within`],
+/// keyed by the paths the device uses for them.
+///
+/// [`Device::open_within`]: struct.Device.html#method.open_within
+pub(crate) type OpenFiles = BTreeMap<PathBuf, Mutex<File>>;
+
+/// Returns `true` if the process runs with privileges it was not started
+/// with, i.e. as a setuid or setgid binary.
+pub fn is_setuid() -> bool {
+    sys::real_uid() != sys::effective_uid() || sys::real_gid() != sys::effective_gid()
+}
+
+/// Removes the environment variables that could influence a privileged
+/// process, and resets `PATH` to the system directories.
+///
+/// Only `HOME`, `XDG_STATE_HOME` and `XDG_CONFIG_HOME` are kept, since they
+/// are meant to be read once the privileges are dropped.
+pub fn sanitize_environment() {
+    let names: Vec<OsString> = env::vars_os().map(|(name, _)| name).collect();
+    for name in names {
+        if !KEPT_VARIABLES.iter().any(|kept| name == *kept) {
+            env::remove_var(name);
+        }
+    }
+    env::set_var("PATH", "/usr/bin:/bin");
+}
+
+/// Permanently gives up the privileges of a setuid or setgid process,
+/// switching to the real user and group IDs.
+///
+/// The supplementary groups are cleared when the process runs as root.
+/// An error is returned if the privileges could be regained afterwards.
+pub fn drop_privileges() -> io::Result<()> {
+    sys::drop_privileges()
+}
+
+impl Device {
+    /// Resolves the device and opens its attribute files, making sure that
+    /// they all lie inside `root`.
+    ///
+    /// The device and its attributes are canonicalized, so that a symbolic
+    /// link pointing outside of `root`, or an attribute pointing outside of
+    /// the device, results in [`Error::Untrusted`]. The returned device
+    /// reads and writes through the open files only, which keep working
+    /// after the privileges are dropped with [`drop_privileges`].
+    /// The missing optional attributes are reported as [`Error::NotFound`]
+    /// when they are read.
+    ///
+    /// The files of the writable attributes are opened for writing unless
+    /// the device is read-only.
+    ///
+    /// [`Error::Untrusted`]: enum.Error.html#variant.Untrusted
+    /// [`Error::NotFound`]: enum.Error.html#variant.NotFound
+    /// [`drop_privileges`]: fn.drop_privileges.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_lcd::{Device, Error};
+    /// # use std::fs;
+    /// let root = std::env::temp_dir().join("rust-lcd-doc-open-within");
+    /// # let _ = fs::remove_dir_all(&root);
+    /// let path = root.join("intel_backlight");
+    /// fs::create_dir_all(&path).unwrap();
+    /// fs::write(path.join("max_brightness"), "100\n").unwrap();
+    /// fs::write(path.join("brightness"), "100\n").unwrap();
+    /// fs::write(path.join("bl_power"), "0\n").unwrap();
+    ///
+    /// let dev = Device::new(&path).open_within(&root).unwrap();
+    /// dev.set_brightness(7).unwrap();
+    /// assert_eq!(fs::read_to_string(path.join("brightness")).unwrap(), "7");
+    ///
+    /// // A device whose attributes lead elsewhere is refused.
+    /// fs::remove_file(path.join("brightness")).unwrap();
+    /// std::os::unix::fs::symlink("/etc/passwd", path.join("brightness")).unwrap();
+    /// match Device::new(&path).open_within(&root) {
+    ///     Err(Error::Untrusted { target, .. }) => assert_eq!(target, std::path::Path::new("/etc/passwd")),
+    ///     other => panic!("unexpected result: {:?}", other),
+    /// }
+    /// # fs::remove_dir_all(&root).unwrap();
+    /// ```
+    pub fn open_within<P: AsRef<Path>>(&self, root: P) -> Result<Device> {
+        let writable = !self.read_only;
+        let attributes = [
+            (self.bl_power.clone(), writable),
+            (self.brightness.clone(), writable),
+            (self.max_brightness.clone(), false),
+            (self.path.join(ACTUAL_BRIGHTNESS), false),
+            (self.path.join(TYPE), false),
+        ];
+        let files = open_files(&self.path, root.as_ref(), &attributes)?;
+        let mut device = self.clone();
+        device.files = Some(Arc::new(files));
+        Ok(device)
+    }
+}
+
+/// Opens the `attributes` of the device at `path`, given with whether
+/// they must be writable, after checking that they all lie inside `root`.
+///
+/// The missing attributes are skipped.
+pub(crate) fn open_files(
+    path: &Path,
+    root: &Path,
+    attributes: &[(PathBuf, bool)],
+) -> Result<OpenFiles> {
+    let root = fs::canonicalize(root).map_err(|e| Error::io(root, e))?;
+    let dir = fs::canonicalize(path).map_err(|e| Error::io(path, e))?;
+    if !dir.starts_with(&root) {
+        return Err(Error::Untrusted {
+            path: path.to_path_buf(),
+            target: dir,
+        });
+    }
+
+    let mut files = OpenFiles::new();
+    for (path, writable) in attributes {
+        let target = match fs::canonicalize(path) {
+            Ok(target) => target,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(Error::io(path, e)),
+        };
+        if !target.starts_with(&dir) {
+            return Err(Error::Untrusted {
+                path: path.clone(),
+                target,
+            });
+        }
+        let file = OpenOptions::new()
+            .read(true)
+            .write(*writable)
+            .open(&target)
+            .map_err(|e| Error::io(path, e))?;
+        files.insert(path.clone(), Mutex::new(file));
+    }
+    Ok(files)
+}
+
+/// Reads the whole content of an open attribute file.
+pub(crate) fn read_open(files: &OpenFiles, path: &Path) -> Result<String> {
+    let file = files.get(path).ok_or_else(|| Error::NotFound {
+        path: path.to_path_buf(),
+    })?;
+    let mut file = file.lock().unwrap();
+    let mut content = String::new();
+    // Seeking back to the start makes sysfs produce the current value again.
+    file.seek(SeekFrom::Start(0))
+        .and_then(|_| file.read_to_string(&mut content))
+        .map_err(|e| Error::io(path, e))?;
+    Ok(content)
+}
+
+/// Replaces the content of an open attribute file.
+pub(crate) fn write_open(files: &OpenFiles, path: &Path, content: &str) -> Result<()> {
+    let file = files.get(path).ok_or_else(|| Error::NotFound {
+        path: path.to_path_buf(),
+    })?;
+    let mut file = file.lock().unwrap();
+    file.seek(SeekFrom::Start(0))
+        .map_err(|e| Error::io(path, e))?;
+    // Sysfs attributes take each write as a whole new value and may not
+    // support truncation; regular files need it to drop a longer old value.
+    let _ = file.set_len(0);
+    file.write_all(content.as_bytes())
+        .map_err(|e| Error::io(path, e))
+}
@@ -0,0 +1,108 @@
+//! Snapshots of the attributes of a device.
+
+use crate::{Backlight, BacklightType, PowerState};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// The attributes of a [`Device`] read at one instant.
+///
+/// Unlike [`Device`], which reads the files every time, a snapshot is a
+/// plain value that can be stored, compared and, with the `serde` feature,
+/// serialized. The attributes that cannot be read are `None`.
+///
+/// [`Device`]: struct.Device.html
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{Backlight, Device, PowerState};
+/// # use std::fs;
+/// let path = std::env::temp_dir().join("rust-lcd-doc-snapshot");
+/// # fs::create_dir_all(&path).unwrap();
+/// fs::write(path.join("max_brightness"), "100\n").unwrap();
+/// fs::write(path.join("brightness"), "42\n").unwrap();
+/// fs::write(path.join("bl_power"), "4\n").unwrap();
+/// let snapshot = Device::new(&path).snapshot();
+/// assert_eq!(snapshot.brightness(), Some(42));
+/// assert_eq!(snapshot.power(), Some(PowerState::Powerdown));
+/// assert_eq!(snapshot.actual_brightness(), None);
+/// # fs::remove_dir_all(&path).unwrap();
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DeviceSnapshot {
+    name: String,
+    path: Option<PathBuf>,
+    id: Option<String>,
+    #[cfg_attr(feature = "serde", serde(rename = "type"))]
+    backlight_type: Option<BacklightType>,
+    power: Option<PowerState>,
+    brightness: Option<i32>,
+    actual_brightness: Option<i32>,
+    max_brightness: Option<i32>,
+    taken_at: SystemTime,
+}
+
+impl DeviceSnapshot {
+    /// Returns the name of the device.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the path of the device, if it is backed by a folder.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns the stable identifier of the device.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+
+    /// Returns the type of the device.
+    pub fn backlight_type(&self) -> Option<BacklightType> {
+        self.backlight_type
+    }
+
+    /// Returns the power state of the device.
+    pub fn power(&self) -> Option<PowerState> {
+        self.power
+    }
+
+    /// Returns the raw brightness of the device.
+    pub fn brightness(&self) -> Option<i32> {
+        self.brightness
+    }
+
+    /// Returns the brightness actually reported by the hardware.
+    pub fn actual_brightness(&self) -> Option<i32> {
+        self.actual_brightness
+    }
+
+    /// Returns the maximum brightness of the device.
+    pub fn max_brightness(&self) -> Option<i32> {
+        self.max_brightness
+    }
+
+    /// Returns the instant at which the snapshot was taken.
+    pub fn taken_at(&self) -> SystemTime {
+        self.taken_at
+    }
+}
+
+/// Reads all the attributes of `device`, for [`Backlight::snapshot`].
+///
+/// [`Backlight::snapshot`]: trait.Backlight.html#method.snapshot
+pub(crate) fn take<B: Backlight + ?Sized>(device: &B) -> DeviceSnapshot {
+    DeviceSnapshot {
+        name: device.name().to_string(),
+        path: device.path().map(Path::to_path_buf),
+        id: device.id().ok(),
+        backlight_type: device.backlight_type().ok(),
+        power: device.power_state().ok(),
+        brightness: device.brightness().ok(),
+        actual_brightness: device.actual_brightness().ok(),
+        max_brightness: device.max_brightness().ok(),
+        taken_at: SystemTime::now(),
+    }
+}
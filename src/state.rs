@@ -0,0 +1,261 @@
+//! Saving and restoring the state of the devices.
+
+use crate::{sys, Backlight, Error, PowerState, Result};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The state file used by root, see [`default_state_path`].
+///
+/// [`default_state_path`]: fn.default_state_path.html
+pub const SYSTEM_STATE_PATH: &str = "/var/lib/rust-lcd/state";
+
+/// The saved state of a single device.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SavedDevice {
+    /// The stable identifier of the device, see [`Device::stable_id`].
+    ///
+    /// [`Device::stable_id`]: struct.Device.html#method.stable_id
+    pub id: String,
+    /// The raw brightness, if it could be read.
+    pub brightness: Option<i32>,
+    /// The power state, if it could be read.
+    pub power: Option<PowerState>,
+}
+
+/// The saved state of a set of devices, keyed by their stable identifier.
+///
+/// The state is stored as a text file with one line per device, holding
+/// the identifier, the raw brightness and the power state separated by tabs.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::{Device, DeviceBuilder, State};
+/// # use std::fs;
+/// let root = std::env::temp_dir().join("rust-lcd-doc-state");
+/// let path = root.join("intel_backlight");
+/// # fs::create_dir_all(&path).unwrap();
+/// fs::write(path.join("max_brightness"), "100\n").unwrap();
+/// fs::write(path.join("brightness"), "42\n").unwrap();
+/// fs::write(path.join("bl_power"), "0\n").unwrap();
+/// let dev = Device::new(&path);
+///
+/// let mut state = State::default();
+/// state.capture(&[dev.clone()]);
+/// state.save(root.join("state")).unwrap();
+///
+/// dev.set_brightness(7).unwrap();
+/// let state = State::load(root.join("state")).unwrap();
+/// assert_eq!(state.restore(&[dev.clone()]).unwrap(), 1);
+/// assert_eq!(dev.brightness().unwrap(), 42);
+///
+/// // Neither does a device that vanished nor one that cannot be written.
+/// dev.set_brightness(7).unwrap();
+/// let read_only = DeviceBuilder::new().read_only(true).build(&path);
+/// let devices = [Device::new(root.join("gone")), read_only, dev.clone()];
+/// assert!(state.restore(&devices).is_err());
+/// assert_eq!(dev.brightness().unwrap(), 42);
+/// # fs::remove_dir_all(&root).unwrap();
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct State {
+    devices: Vec<SavedDevice>,
+}
+
+impl State {
+    /// Loads the state from `path`.
+    ///
+    /// A missing file is reported as [`Error::NotFound`].
+    ///
+    /// [`Error::NotFound`]: enum.Error.html#variant.NotFound
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let mut state = State::default();
+        for line in content.lines() {
+            let line = line.trim_end();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let saved = parse_line(line).ok_or_else(|| {
+                Error::parse(path, line, "an identifier, a brightness and a power state")
+            })?;
+            state.insert(saved);
+        }
+        Ok(state)
+    }
+
+    /// Loads the state from `path`, or returns an empty state if it does not exist.
+    pub fn load_or_default<P: AsRef<Path>>(path: P) -> Result<Self> {
+        match State::load(path) {
+            Err(Error::NotFound { .. }) => Ok(State::default()),
+            other => other,
+        }
+    }
+
+    /// Saves the state to `path`, creating the parent directories.
+    ///
+    /// The file is replaced atomically, so that a crash cannot leave it half written.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+        }
+        let mut content = String::from("# rust-lcd state: id, brightness, bl_power\n");
+        for saved in &self.devices {
+            content.push_str(&format!(
+                "{}\t{}\t{}\n",
+                saved.id,
+                saved
+                    .brightness
+                    .map_or_else(|| "-".to_string(), |b| b.to_string()),
+                saved
+                    .power
+                    .map_or_else(|| "-".to_string(), |p| i32::from(p).to_string()),
+            ));
+        }
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        let tmp = PathBuf::from(tmp);
+        fs::write(&tmp, content).map_err(|e| Error::io(&tmp, e))?;
+        fs::rename(&tmp, path).map_err(|e| Error::io(path, e))
+    }
+
+    /// Returns the saved devices.
+    pub fn devices(&self) -> &[SavedDevice] {
+        &self.devices
+    }
+
+    /// Returns the saved state of the device with the stable identifier `id`.
+    pub fn get(&self, id: &str) -> Option<&SavedDevice> {
+        self.devices.iter().find(|saved| saved.id == id)
+    }
+
+    /// Inserts the saved state of a device, replacing the one with the same identifier.
+    pub fn insert(&mut self, saved: SavedDevice) {
+        match self.devices.iter_mut().find(|s| s.id == saved.id) {
+            Some(old) => *old = saved,
+            None => self.devices.push(saved),
+        }
+    }
+
+    /// Records the current brightness and power state of `devices`.
+    ///
+    /// The devices that are not in `devices` keep their previous entry, so that
+    /// the state of a device that is currently unplugged is not lost.
+    /// The devices whose identifier cannot be determined are skipped.
+    pub fn capture<'a, I, B>(&mut self, devices: I)
+    where
+        I: IntoIterator<Item = &'a B>,
+        B: Backlight + ?Sized + 'a,
+    {
+        for device in devices {
+            if let Ok(id) = device.id() {
+                self.insert(SavedDevice {
+                    id,
+                    brightness: device.brightness().ok(),
+                    power: device.power_state().ok(),
+                });
+            }
+        }
+    }
+
+    /// Restores the saved brightness and power state of `devices`.
+    ///
+    /// The brightness is restored first and is kept within the floor and the
+    /// ceiling of the device, so that a panel never comes back completely dark.
+    /// Devices without a saved state are skipped. The return value is the
+    /// number of devices that were restored.
+    ///
+    /// A device whose identifier cannot be determined, or that cannot be
+    /// read or written, is skipped as well, and the first such error is
+    /// returned once the other devices are restored.
+    pub fn restore<'a, I, B>(&self, devices: I) -> Result<usize>
+    where
+        I: IntoIterator<Item = &'a B>,
+        B: Backlight + ?Sized + 'a,
+    {
+        let mut restored = 0;
+        let mut failed = None;
+        for device in devices {
+            let id = match device.id() {
+                Ok(id) => id,
+                Err(e) => {
+                    failed.get_or_insert(e);
+                    continue;
+                }
+            };
+            let saved = match self.get(&id) {
+                Some(saved) => saved,
+                None => continue,
+            };
+            match restore_device(device, saved) {
+                Ok(()) => restored += 1,
+                Err(e) => {
+                    failed.get_or_insert(e);
+                }
+            }
+        }
+        match failed {
+            Some(e) => Err(e),
+            None => Ok(restored),
+        }
+    }
+}
+
+/// Restores the brightness, then the power state, of a single device.
+fn restore_device<B: Backlight + ?Sized>(device: &B, saved: &SavedDevice) -> Result<()> {
+    if let Some(brightness) = saved.brightness {
+        let max = device.ceiling_brightness()?;
+        let min = device.min_brightness()?;
+        device.set_brightness(brightness.clamp(min, max))?;
+    }
+    if let Some(power) = saved.power {
+        device.set_power_state(power)?;
+    }
+    Ok(())
+}
+
+fn parse_line(line: &str) -> Option<SavedDevice> {
+    let mut fields = line.split('\t');
+    let id = fields.next()?.to_string();
+    let brightness = match fields.next()? {
+        "-" => None,
+        value => Some(value.parse().ok()?),
+    };
+    let power = match fields.next()? {
+        "-" => None,
+        value => Some(value.parse().ok()?),
+    };
+    if id.is_empty() || fields.next().is_some() {
+        return None;
+    }
+    Some(SavedDevice {
+        id,
+        brightness,
+        power,
+    })
+}
+
+/// Returns the default location of the state file.
+///
+/// When running as root this is [`SYSTEM_STATE_PATH`]; otherwise it is
+/// `rust-lcd/state` inside `$XDG_STATE_HOME`, which defaults to
+/// `$HOME/.local/state`.
+///
+/// [`SYSTEM_STATE_PATH`]: constant.SYSTEM_STATE_PATH.html
+pub fn default_state_path() -> PathBuf {
+    if sys::effective_uid() != 0 {
+        let state_home = env::var_os("XDG_STATE_HOME")
+            .map(PathBuf::from)
+            .filter(|dir| dir.is_absolute())
+            .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/state")));
+        if let Some(dir) = state_home {
+            return dir.join("rust-lcd").join("state");
+        }
+    }
+    PathBuf::from(SYSTEM_STATE_PATH)
+}
@@ -0,0 +1,114 @@
+//! Relative changes of the brightness.
+
+use crate::{Backlight, Result};
+use std::error;
+use std::fmt;
+use std::ops::Neg;
+use std::str::FromStr;
+
+/// A signed change of the brightness, relative to its current value.
+///
+/// # Examples
+///
+/// ```
+/// # use rust_lcd::Step;
+/// assert_eq!("+5%".parse(), Ok(Step::Percent(5.0)));
+/// assert_eq!("-10".parse(), Ok(Step::Raw(-10)));
+/// assert_eq!(Step::Percent(-2.5).to_string(), "-2.5%");
+/// assert!("5".parse::<Step>().is_err()); // the sign is mandatory
+/// assert_eq!(-Step::Raw(10), Step::Raw(-10));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum Step {
+    /// A change of the raw brightness value.
+    Raw(i32),
+    /// A change of the percentage, according to the [`Scale`] of the device.
+    ///
+    /// [`Scale`]: enum.Scale.html
+    Percent(f64),
+}
+
+impl fmt::Display for Step {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Step::Raw(delta) => write!(f, "{:+}", delta),
+            Step::Percent(delta) => write!(f, "{:+}%", delta),
+        }
+    }
+}
+
+impl Neg for Step {
+    type Output = Step;
+
+    fn neg(self) -> Step {
+        match self {
+            Step::Raw(delta) => Step::Raw(-delta),
+            Step::Percent(delta) => Step::Percent(-delta),
+        }
+    }
+}
+
+impl FromStr for Step {
+    type Err = ParseStepError;
+
+    /// Parses a step like `+5%`, `-5%`, `+10` or `-10`.
+    ///
+    /// The sign is mandatory, so that a step cannot be mistaken for an
+    /// absolute value.
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let err = || ParseStepError(s.to_string());
+        let t = s.trim();
+        if !t.starts_with('+') && !t.starts_with('-') {
+            return Err(err());
+        }
+        match t.strip_suffix('%') {
+            Some(percent) => percent
+                .parse::<f64>()
+                .ok()
+                .filter(|delta| delta.is_finite())
+                .map(Step::Percent)
+                .ok_or_else(err),
+            None => t.parse().map(Step::Raw).map_err(|_| err()),
+        }
+    }
+}
+
+/// The error returned when a [`Step`] cannot be parsed.
+///
+/// [`Step`]: enum.Step.html
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseStepError(String);
+
+impl fmt::Display for ParseStepError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid step '{}'", self.0)
+    }
+}
+
+impl error::Error for ParseStepError {}
+
+/// Computes the target of [`Backlight::step`].
+///
+/// [`Backlight::step`]: trait.Backlight.html#method.step
+pub(crate) fn step_target<B: Backlight + ?Sized>(device: &B, delta: Step) -> Result<i32> {
+    let current = device.brightness()?;
+    let min = device.min_brightness()?.min(current);
+    let max = device.ceiling_brightness()?.max(current);
+    let target = match delta {
+        Step::Raw(delta) => current.saturating_add(delta),
+        Step::Percent(delta) => {
+            let percent = device.raw_to_percent(current)?;
+            let target = device.percent_to_raw(percent + delta)?;
+            if delta > 0.0 && target <= current {
+                current.saturating_add(1)
+            } else if delta < 0.0 && target >= current {
+                current.saturating_sub(1)
+            } else {
+                target
+            }
+        }
+    };
+    Ok(target.clamp(min, max))
+}
@@ -0,0 +1,339 @@
+//! Thin bindings to the C library.
+//!
+//! The standard library already links against it, so declaring the few
+//! functions needed here avoids depending on the `libc` crate.
+
+use std::ffi::CString;
+use std::fs::File;
+use std::io::{self, Read};
+use std::os::raw::{c_char, c_int, c_short, c_uint, c_ulong, c_void};
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::path::Path;
+use std::time::Duration;
+
+const IN_NONBLOCK: c_int = 0o4000;
+const IN_CLOEXEC: c_int = 0o2000000;
+const IN_MODIFY: u32 = 0x0000_0002;
+const IN_ATTRIB: u32 = 0x0000_0004;
+const IN_MOVED_FROM: u32 = 0x0000_0040;
+const IN_MOVED_TO: u32 = 0x0000_0080;
+const IN_CREATE: u32 = 0x0000_0100;
+const IN_DELETE: u32 = 0x0000_0200;
+const POLLIN: c_short = 0x001;
+
+/// The `ioctl` selecting the address of the peer on an i2c-dev node.
+const I2C_SLAVE: c_ulong = 0x0703;
+
+const AF_NETLINK: c_int = 16;
+const SOCK_DGRAM: c_int = 2;
+const SOCK_NONBLOCK: c_int = 0o4000;
+const SOCK_CLOEXEC: c_int = 0o2000000;
+const NETLINK_KOBJECT_UEVENT: c_int = 15;
+/// The multicast group on which the kernel broadcasts its uevents.
+const UEVENT_KERNEL_GROUP: u32 = 1;
+
+const SOL_SOCKET: c_int = 1;
+const SO_PEERCRED: c_int = 17;
+const SO_PEERGROUPS: c_int = 59;
+
+pub(crate) const SIGINT: c_int = 2;
+pub(crate) const SIGTERM: c_int = 15;
+/// The handler restoring the default action of a signal.
+const SIG_DFL: usize = 0;
+
+#[repr(C)]
+struct PollFd {
+    fd: c_int,
+    events: c_short,
+    revents: c_short,
+}
+
+#[repr(C)]
+struct Ucred {
+    pid: i32,
+    uid: u32,
+    gid: u32,
+}
+
+#[repr(C)]
+struct SockaddrNl {
+    nl_family: u16,
+    nl_pad: u16,
+    nl_pid: u32,
+    nl_groups: u32,
+}
+
+extern "C" {
+    fn getuid() -> c_uint;
+    fn geteuid() -> c_uint;
+    fn getgid() -> c_uint;
+    fn getegid() -> c_uint;
+    fn setuid(uid: c_uint) -> c_int;
+    fn setgid(gid: c_uint) -> c_int;
+    fn setresuid(ruid: c_uint, euid: c_uint, suid: c_uint) -> c_int;
+    fn setresgid(rgid: c_uint, egid: c_uint, sgid: c_uint) -> c_int;
+    fn setgroups(size: usize, list: *const c_uint) -> c_int;
+    fn socket(domain: c_int, kind: c_int, protocol: c_int) -> c_int;
+    fn bind(fd: c_int, addr: *const SockaddrNl, len: c_uint) -> c_int;
+    fn getsockopt(
+        fd: c_int,
+        level: c_int,
+        name: c_int,
+        value: *mut c_void,
+        len: *mut c_uint,
+    ) -> c_int;
+    fn inotify_init1(flags: c_int) -> c_int;
+    fn inotify_add_watch(fd: c_int, pathname: *const c_char, mask: u32) -> c_int;
+    fn poll(fds: *mut PollFd, nfds: c_ulong, timeout: c_int) -> c_int;
+    fn ioctl(fd: c_int, request: c_ulong, ...) -> c_int;
+    fn signal(signum: c_int, handler: usize) -> usize;
+}
+
+/// Returns the real user ID of the process.
+pub(crate) fn real_uid() -> u32 {
+    unsafe { getuid() }
+}
+
+/// Returns the effective user ID of the process.
+pub(crate) fn effective_uid() -> u32 {
+    unsafe { geteuid() }
+}
+
+/// Returns the real group ID of the process.
+pub(crate) fn real_gid() -> u32 {
+    unsafe { getgid() }
+}
+
+/// Returns the effective group ID of the process.
+pub(crate) fn effective_gid() -> u32 {
+    unsafe { getegid() }
+}
+
+/// Switches the process to its real user and group IDs for good,
+/// including the saved set-user-ID and set-group-ID.
+pub(crate) fn drop_privileges() -> io::Result<()> {
+    let (uid, gid) = (real_uid(), real_gid());
+    let (euid, egid) = (effective_uid(), effective_gid());
+    if euid == 0 && uid != 0 && unsafe { setgroups(0, std::ptr::null()) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // The group goes first, since changing it requires the user privileges.
+    if unsafe { setresgid(gid, gid, gid) } != 0 || unsafe { setresuid(uid, uid, uid) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Root may take any ID back, so there is nothing to check for it.
+    let regained = uid != 0
+        && ((euid != uid && unsafe { setuid(euid) } == 0)
+            || (egid != gid && unsafe { setgid(egid) } == 0));
+    if regained {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "the privileges could be regained after dropping them",
+        ));
+    }
+    Ok(())
+}
+
+/// Waits until `file` is readable (or, for a listener, has a pending
+/// connection) or `timeout` expires.
+///
+/// Returns `true` if the file is readable. An interrupted wait counts as a
+/// timeout, so that the caller gets a chance to check its stop conditions.
+pub(crate) fn wait_readable<F: AsRawFd>(file: &F, timeout: Duration) -> io::Result<bool> {
+    let mut fds = PollFd {
+        fd: file.as_raw_fd(),
+        events: POLLIN,
+        revents: 0,
+    };
+    let timeout = timeout.as_millis().min(c_int::MAX as u128) as c_int;
+    match unsafe { poll(&mut fds, 1, timeout) } {
+        -1 => {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                Ok(false)
+            } else {
+                Err(e)
+            }
+        }
+        0 => Ok(false),
+        _ => Ok(fds.revents & POLLIN != 0),
+    }
+}
+
+/// The user and groups of the peer of a Unix socket, as of its connection.
+pub(crate) struct PeerCredentials {
+    pub(crate) uid: u32,
+    /// The primary group, then the supplementary ones if the kernel reports them.
+    pub(crate) groups: Vec<u32>,
+}
+
+/// Returns the credentials of the peer of the connected Unix `socket`.
+pub(crate) fn peer_credentials<F: AsRawFd>(socket: &F) -> io::Result<PeerCredentials> {
+    let fd = socket.as_raw_fd();
+    let mut cred = Ucred {
+        pid: 0,
+        uid: 0,
+        gid: 0,
+    };
+    let mut len = std::mem::size_of::<Ucred>() as c_uint;
+    let value = &mut cred as *mut Ucred as *mut c_void;
+    if unsafe { getsockopt(fd, SOL_SOCKET, SO_PEERCRED, value, &mut len) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut groups = vec![cred.gid];
+    let mut buf = [0u32; 64];
+    let mut len = std::mem::size_of_val(&buf) as c_uint;
+    // The supplementary groups are only reported since Linux 4.13.
+    let value = buf.as_mut_ptr() as *mut c_void;
+    if unsafe { getsockopt(fd, SOL_SOCKET, SO_PEERGROUPS, value, &mut len) } == 0 {
+        groups.extend_from_slice(&buf[..len as usize / std::mem::size_of::<u32>()]);
+    }
+    Ok(PeerCredentials {
+        uid: cred.uid,
+        groups,
+    })
+}
+
+/// Runs `handler` when the process receives the signal `signum`.
+pub(crate) fn set_signal_handler(signum: c_int, handler: extern "C" fn(c_int)) {
+    unsafe { signal(signum, handler as usize) };
+}
+
+/// Restores the default action of the signal `signum`.
+pub(crate) fn reset_signal_handler(signum: c_int) {
+    unsafe { signal(signum, SIG_DFL) };
+}
+
+/// Directs the next reads and writes on the i2c-dev node `file` to the
+/// peer at `address`.
+pub(crate) fn i2c_set_address(file: &File, address: u16) -> io::Result<()> {
+    if unsafe { ioctl(file.as_raw_fd(), I2C_SLAVE, c_ulong::from(address)) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(())
+}
+
+/// A non-blocking inotify instance, closed when dropped.
+pub(crate) struct Inotify {
+    file: File,
+}
+
+impl Inotify {
+    pub(crate) fn new() -> io::Result<Self> {
+        let fd = unsafe { inotify_init1(IN_NONBLOCK | IN_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self {
+            file: unsafe { File::from_raw_fd(fd) },
+        })
+    }
+
+    /// Watches `path` for modifications of its content or metadata.
+    pub(crate) fn add_watch(&self, path: &Path) -> io::Result<()> {
+        self.add_watch_mask(path, IN_MODIFY | IN_ATTRIB)
+    }
+
+    /// Watches the directory `path` for entries being added or removed.
+    pub(crate) fn add_dir_watch(&self, path: &Path) -> io::Result<()> {
+        self.add_watch_mask(path, IN_CREATE | IN_DELETE | IN_MOVED_FROM | IN_MOVED_TO)
+    }
+
+    fn add_watch_mask(&self, path: &Path, mask: u32) -> io::Result<()> {
+        let path = CString::new(path.as_os_str().as_bytes())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+        let wd = unsafe { inotify_add_watch(self.file.as_raw_fd(), path.as_ptr(), mask) };
+        if wd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+
+    /// Waits for events for at most `timeout` and discards them.
+    ///
+    /// After the first event, the events keep being discarded until none
+    /// arrives for a few milliseconds, so that a burst of writes (like a
+    /// truncation followed by a write) is reported once.
+    /// Returns `true` if any event arrived.
+    pub(crate) fn wait(&mut self, timeout: Duration) -> io::Result<bool> {
+        if !wait_readable(&self.file, timeout)? {
+            return Ok(false);
+        }
+        loop {
+            self.drain()?;
+            if !wait_readable(&self.file, Duration::from_millis(5))? {
+                return Ok(true);
+            }
+        }
+    }
+
+    fn drain(&mut self) -> io::Result<()> {
+        let mut buf = [0u8; 4096];
+        loop {
+            match self.file.read(&mut buf) {
+                Ok(_) => continue,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(()),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// A non-blocking netlink socket receiving the kernel uevents, closed when dropped.
+pub(crate) struct UeventSocket {
+    file: File,
+}
+
+impl UeventSocket {
+    pub(crate) fn new() -> io::Result<Self> {
+        let fd = unsafe {
+            socket(
+                AF_NETLINK,
+                SOCK_DGRAM | SOCK_NONBLOCK | SOCK_CLOEXEC,
+                NETLINK_KOBJECT_UEVENT,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let file = unsafe { File::from_raw_fd(fd) };
+        let addr = SockaddrNl {
+            nl_family: AF_NETLINK as u16,
+            nl_pad: 0,
+            nl_pid: 0,
+            nl_groups: UEVENT_KERNEL_GROUP,
+        };
+        let len = std::mem::size_of::<SockaddrNl>() as c_uint;
+        if unsafe { bind(file.as_raw_fd(), &addr, len) } < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(Self { file })
+    }
+
+    /// Waits for uevents for at most `timeout`.
+    ///
+    /// Returns `true` if any of the uevents that arrived concerns `subsystem`.
+    pub(crate) fn wait(&mut self, timeout: Duration, subsystem: &str) -> io::Result<bool> {
+        if !wait_readable(&self.file, timeout)? {
+            return Ok(false);
+        }
+        let wanted = format!("SUBSYSTEM={}", subsystem);
+        let mut relevant = false;
+        let mut buf = [0u8; 8192];
+        loop {
+            match self.file.read(&mut buf) {
+                // A uevent is a header followed by `KEY=VALUE` fields, all NUL-terminated.
+                Ok(n) => {
+                    relevant |= buf[..n]
+                        .split(|&b| b == 0)
+                        .any(|field| field == wanted.as_bytes())
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(relevant),
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
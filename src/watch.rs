@@ -0,0 +1,293 @@
+//! Watching devices for changes.
+
+use crate::{sys, Backlight, Device, Error, Result};
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+/// The default interval between two reads of the attributes of a device.
+pub const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// An attribute of a device that can change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Attribute {
+    /// The requested brightness, from [`BRIGHTNESS`].
+    ///
+    /// [`BRIGHTNESS`]: constant.BRIGHTNESS.html
+    Brightness,
+    /// The brightness reported by the hardware, from [`ACTUAL_BRIGHTNESS`].
+    ///
+    /// [`ACTUAL_BRIGHTNESS`]: constant.ACTUAL_BRIGHTNESS.html
+    ActualBrightness,
+    /// The raw power state, from [`BL_POWER`].
+    ///
+    /// [`BL_POWER`]: constant.BL_POWER.html
+    Power,
+}
+
+impl Attribute {
+    const ALL: [Attribute; 3] = [
+        Attribute::Brightness,
+        Attribute::ActualBrightness,
+        Attribute::Power,
+    ];
+
+    /// Returns the name of the attribute, which is also the default file name.
+    pub fn name(self) -> &'static str {
+        match self {
+            Attribute::Brightness => "brightness",
+            Attribute::ActualBrightness => "actual_brightness",
+            Attribute::Power => "bl_power",
+        }
+    }
+
+    fn read(self, device: &dyn Backlight) -> Option<i32> {
+        match self {
+            Attribute::Brightness => device.brightness().ok(),
+            Attribute::ActualBrightness => device.actual_brightness().ok(),
+            Attribute::Power => device.power_state().ok().map(i32::from),
+        }
+    }
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A change of an attribute of a device.
+///
+/// A value is `None` when the attribute could not be read, for instance
+/// because the device disappeared.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChangeEvent {
+    /// The name of the device.
+    pub device: String,
+    /// The attribute that changed.
+    pub attribute: Attribute,
+    /// The value before the change.
+    pub old: Option<i32>,
+    /// The value after the change.
+    pub new: Option<i32>,
+    /// The instant at which the change was detected.
+    pub timestamp: SystemTime,
+}
+
+/// How a [`Watcher`] is notified of changes.
+///
+/// [`Watcher`]: struct.Watcher.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchBackend {
+    /// The attributes are watched with inotify, and are also read every
+    /// interval to catch the ones that the kernel does not notify.
+    Inotify,
+    /// The attributes are only read every interval.
+    Polling,
+}
+
+/// The last values read from a device, in the order of `Attribute::ALL`.
+type Values = [Option<i32>; 3];
+
+/// A blocking iterator over the changes of the attributes of some devices.
+///
+/// Writes through the filesystem, for instance by another tool, are noticed
+/// immediately through inotify. Most sysfs attributes, like `actual_brightness`
+/// changed by firmware hotkeys, do not notify, so every attribute is also
+/// read again at each interval. If inotify is not available the watcher
+/// falls back to polling alone, as it does for the backlights without
+/// [`watched_paths`].
+///
+/// [`watched_paths`]: trait.Backlight.html#method.watched_paths
+///
+/// # Examples
+///
+/// ```no_run
+/// # use rust_lcd::DeviceIter;
+/// let device = DeviceIter::default().next().unwrap();
+/// for event in device.watch().unwrap() {
+///     let event = event.unwrap();
+///     println!("{} {}: {:?} -> {:?}", event.device, event.attribute, event.old, event.new);
+/// }
+/// ```
+pub struct Watcher<'a> {
+    devices: Vec<(Box<dyn Backlight>, Values)>,
+    inotify: Option<sys::Inotify>,
+    interval: Duration,
+    stop: Option<&'a AtomicBool>,
+    pending: VecDeque<ChangeEvent>,
+}
+
+impl<'a> Watcher<'a> {
+    /// Creates a watcher over `devices`, reading their current values.
+    ///
+    /// Inotify is used when available, otherwise the watcher polls.
+    pub fn new<I, B>(devices: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = B>,
+        B: Backlight + 'static,
+    {
+        Self::with_inotify(devices, sys::Inotify::new().ok())
+    }
+
+    /// Creates a watcher that only polls, without trying inotify.
+    pub fn polling<I, B>(devices: I) -> Result<Self>
+    where
+        I: IntoIterator<Item = B>,
+        B: Backlight + 'static,
+    {
+        Self::with_inotify(devices, None)
+    }
+
+    fn with_inotify<I, B>(devices: I, inotify: Option<sys::Inotify>) -> Result<Self>
+    where
+        I: IntoIterator<Item = B>,
+        B: Backlight + 'static,
+    {
+        let devices: Vec<_> = devices
+            .into_iter()
+            .map(|device| {
+                let device: Box<dyn Backlight> = Box::new(device);
+                let values = Attribute::ALL.map(|attribute| attribute.read(&*device));
+                (device, values)
+            })
+            .collect();
+        if let Some(ref inotify) = inotify {
+            for (device, _) in &devices {
+                for path in device.watched_paths() {
+                    match inotify.add_watch(&path) {
+                        // Missing attributes are simply never notified.
+                        Err(ref e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                        Err(e) => return Err(Error::io(path, e)),
+                        Ok(()) => {}
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            devices,
+            inotify,
+            interval: WATCH_INTERVAL,
+            stop: None,
+            pending: VecDeque::new(),
+        })
+    }
+
+    /// Sets the interval between two reads of the attributes,
+    /// [`WATCH_INTERVAL`] by default.
+    ///
+    /// [`WATCH_INTERVAL`]: constant.WATCH_INTERVAL.html
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Makes the iterator end as soon as `stop` becomes `true`.
+    ///
+    /// This is meant to be used with a flag raised by a signal handler or by
+    /// another thread; the flag is checked at least once per interval.
+    pub fn until(mut self, stop: &'a AtomicBool) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Returns the backend used to detect changes.
+    pub fn backend(&self) -> WatchBackend {
+        if self.inotify.is_some() {
+            WatchBackend::Inotify
+        } else {
+            WatchBackend::Polling
+        }
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.is_some_and(|stop| stop.load(Ordering::SeqCst))
+    }
+
+    /// Reads all the attributes again and queues an event for each change.
+    fn scan(&mut self) {
+        let timestamp = SystemTime::now();
+        for (device, values) in &mut self.devices {
+            for (attribute, old) in Attribute::ALL.iter().zip(values.iter_mut()) {
+                let new = attribute.read(&**device);
+                if new != *old {
+                    self.pending.push_back(ChangeEvent {
+                        device: device.name().to_string(),
+                        attribute: *attribute,
+                        old: *old,
+                        new,
+                        timestamp,
+                    });
+                    *old = new;
+                }
+            }
+        }
+    }
+}
+
+impl Iterator for Watcher<'_> {
+    type Item = Result<ChangeEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(Ok(event));
+            }
+            if self.stopped() {
+                return None;
+            }
+            let deadline = Instant::now() + self.interval;
+            match self.inotify {
+                Some(ref mut inotify) => {
+                    if let Err(e) = inotify.wait(self.interval) {
+                        return Some(Err(Error::io("inotify", e)));
+                    }
+                }
+                None => {
+                    // Sleep in short slices, so that the stop flag is noticed quickly.
+                    while !self.stopped() {
+                        let now = Instant::now();
+                        if now >= deadline {
+                            break;
+                        }
+                        std::thread::sleep((deadline - now).min(Duration::from_millis(50)));
+                    }
+                }
+            }
+            self.scan();
+        }
+    }
+}
+
+impl Device {
+    /// Watches the attributes of the device for changes.
+    ///
+    /// See [`Watcher`] for the details.
+    ///
+    /// [`Watcher`]: struct.Watcher.html
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use rust_lcd::{Attribute, Device};
+    /// # use std::fs;
+    /// use std::time::Duration;
+    /// let path = std::env::temp_dir().join("rust-lcd-doc-watch");
+    /// # fs::create_dir_all(&path).unwrap();
+    /// fs::write(path.join("brightness"), "42\n").unwrap();
+    /// let dev = Device::new(&path);
+    /// let mut watcher = dev.watch().unwrap().interval(Duration::from_millis(10));
+    /// fs::write(path.join("brightness"), "7\n").unwrap();
+    /// let event = watcher.next().unwrap().unwrap();
+    /// assert_eq!(event.attribute, Attribute::Brightness);
+    /// assert_eq!((event.old, event.new), (Some(42), Some(7)));
+    /// # fs::remove_dir_all(&path).unwrap();
+    /// ```
+    pub fn watch(&self) -> Result<Watcher<'static>> {
+        Watcher::new(vec![self.clone()])
+    }
+}